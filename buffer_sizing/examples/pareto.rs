@@ -1,6 +1,9 @@
-use grb::Expr;
+use buffer_sizing::pareto::ParetoOptions;
+use buffer_sizing::BufferedMrsdf;
+use milp_formulation::solver::DefaultSolver;
+use milp_formulation::MilpFormulation;
 use sdf3_xml_parser::parse;
-use grb::prelude::*;
+use std::borrow::Cow;
 
 fn main() -> anyhow::Result<()> {
     let Some(file) = std::env::args().nth(1) else {
@@ -9,47 +12,55 @@ fn main() -> anyhow::Result<()> {
 
     let s = std::fs::read_to_string(file)?;
 
-    let (mut milp, execution_time, buffers) = parse(&s);
+    let graph = parse(&s)?;
+    let (sdf, channels) = graph.mdsdf();
+    let execution_time = graph
+        .actors
+        .iter()
+        .map(|a| a.default_processor().map(|p| p.execution_time))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| anyhow::anyhow!("an actor has no execution time"))?;
 
     println!("Parsed");
 
-    let throughput = &milp.throughputs[0];
-
-    let buffer_size = buffers.iter().sum::<Expr>();
-    
-    let mut cycle_time_ub = milp.hsdf.repetition_vector.iter().enumerate().map(|(i, r)| r[0] * execution_time.get(&i).unwrap()).sum::<usize>();
-    
-    let model = &mut milp.model;
-    let mut cycle_time_constraint= model.add_constr("no_deadlock", grb::c!(throughput.clone()*cycle_time_ub >= 1))?;
-    let mut capacity_constraint= model.add_constr("capacity", c!(buffer_size.clone() <= grb::INFINITY))?;
-    
-    model.get_env_mut().set(grb::param::LogToConsole, 0)?;
-    //very numerically unstable optimization
-    model.get_env_mut().set(grb::param::IntFeasTol, 1e-9)?;
-    model.get_env_mut().set(grb::param::FeasibilityTol, 1e-9)?;
-    model.get_env_mut().set(grb::param::OptimalityTol, 1e-9)?;
-
-    loop {
-        model.set_objective(0.0*throughput.clone() + buffer_size.clone(), grb::ModelSense::Minimize)?;
-        model.remove(capacity_constraint)?;
-        model.optimize()?;
-        
-        if model.status()? != Status::Optimal {
-            break;
-        }
-        let capacity: usize = model.get_obj_attr_batch(attr::X, buffers.clone())?.iter().map(|e| e.round() as usize).sum();
-        capacity_constraint = model.add_constr("capacity", c!(buffer_size.clone() <= capacity))?;
-        model.set_objective(cycle_time_ub*throughput.clone() + 0.0*buffer_size.clone(), grb::ModelSense::Maximize)?;
-        model.remove(cycle_time_constraint)?;
-        model.optimize()?;
-        if model.status()? != Status::Optimal {
-            break;
-        }
-        let cycle_time = (1.0/model.get_obj_attr(attr::X, throughput)?).round() as usize;
-        cycle_time_ub = cycle_time - 1;
-        cycle_time_constraint = model.add_constr("no_deadlock", grb::c!(throughput.clone()*cycle_time_ub >= 1))?;
-
-        println!("Pareto: {} {}", cycle_time, capacity);
+    let mut milp = MilpFormulation::new(
+        Cow::Owned(sdf.into_hsdf()),
+        |(i, _)| execution_time[i],
+        |(i, j)| format!("{}({})", graph.actors[i].name, j[0]),
+    )?;
+
+    // channels without initial tokens are the ones whose buffers get sized
+    let to_buffer = graph
+        .channels
+        .iter()
+        .zip(channels)
+        .filter(|(c, _)| c.initial_tokens.is_none())
+        .collect::<Vec<_>>();
+    let buffers = to_buffer
+        .iter()
+        .map(|(c, _)| milp.model.add_ctsvar(&c.name, 0.0, f64::INFINITY))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(min_throughput) = graph.throughput {
+        // points slower than the application's throughput constraint are not of interest
+        let throughput = milp.throughputs[0];
+        milp.model
+            .add_constr("min_throughput", throughput.geq(min_throughput))?;
+    }
+
+    let mut buffer_sizing = BufferedMrsdf::new(&mut milp);
+    for ((_, channel), buffer) in to_buffer.iter().zip(buffers.iter()) {
+        buffer_sizing.add_buffer(*channel, [(*buffer).into()].into())?;
+    }
+
+    // tokens of different channels take different amounts of memory
+    let weights = to_buffer
+        .iter()
+        .map(|(c, _)| c.properties.token_size.unwrap_or(1) as f64)
+        .collect::<Vec<_>>();
+    let points =
+        buffer_sizing.pareto::<DefaultSolver>(&buffers, &weights, ParetoOptions::default())?;
+    for point in points {
+        println!("Pareto: {} {}", point.period, point.capacity);
     }
 
     Ok(())
@@ -58,25 +69,26 @@ fn main() -> anyhow::Result<()> {
 #[cfg(test)]
 mod tests {
     #[test]
-    fn test() -> grb::Result<()> {
-        use grb::prelude::*;
+    fn test() -> anyhow::Result<()> {
+        use milp_formulation::model::{Model, ModelSense};
+        use milp_formulation::solver::DefaultSolver;
 
-        let mut model = Model::new("model")?;
+        let mut model = Model::new("model");
 
-        let x = add_ctsvar!(model, name: "x", bounds: 0..)?;
-        let y = add_ctsvar!(model, name: "y", bounds: 0..)?;
+        let x = model.add_ctsvar("x", 0.0, f64::INFINITY)?;
+        let y = model.add_ctsvar("y", 0.0, f64::INFINITY)?;
 
-        model.add_constr("c", c!(x + y <= 10))?;
+        model.add_constr("c", (x + y).leq(10.0))?;
 
-        model.set_objective(2*x, ModelSense::Maximize)?;
-        model.optimize()?;
+        model.set_objective(2.0 * x, ModelSense::Maximize)?;
+        let solution = model.optimize::<DefaultSolver>()?;
 
-        println!("x:{}\ty:{}", model.get_obj_attr(attr::X, &x)?, model.get_obj_attr(attr::X, &y)?);
+        println!("x:{}\ty:{}", solution.value(x).unwrap(), solution.value(y).unwrap());
 
         model.set_objective(y, ModelSense::Maximize)?;
-        model.optimize()?;
+        let solution = model.optimize::<DefaultSolver>()?;
 
-        println!("x:{}\ty:{}", model.get_obj_attr(attr::X, &x)?, model.get_obj_attr(attr::X, &y)?);
+        println!("x:{}\ty:{}", solution.value(x).unwrap(), solution.value(y).unwrap());
 
         Ok(())
     }
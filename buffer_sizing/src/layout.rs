@@ -0,0 +1,190 @@
+use crate::memory::TokenLayout;
+use mdsdf::vector::Vector;
+use std::fmt;
+
+/// Address range buffers are placed in, e.g. the SRAM of a tile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Memory {
+    pub name: String,
+    /// Address of the first byte.
+    pub base: usize,
+    /// Bytes available from `base`.
+    pub size: usize,
+}
+
+/// Buffer to place by [`layout`], with its solved size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Block<const N: usize> {
+    /// Tokens the buffer holds in every dimension.
+    pub tokens: Vector<N, usize>,
+    pub layout: TokenLayout<N>,
+    /// Index of the memory holding the buffer.
+    pub memory: usize,
+}
+
+/// Place of a [`Block`] in its memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub memory: usize,
+    /// Address of the header of the buffer, followed by its first token.
+    pub address: usize,
+    pub bytes: usize,
+}
+
+/// Reason why [`layout`] could not place the blocks. Blocks and memories are given by their
+/// index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    UnknownMemory {
+        block: usize,
+        memory: usize,
+    },
+    /// No free range of `memory` holds the `bytes` of `block` with its tokens aligned, although
+    /// `free` bytes are left in pieces of at most `largest_free` bytes.
+    DoesNotFit {
+        block: usize,
+        memory: String,
+        bytes: usize,
+        free: usize,
+        largest_free: usize,
+    },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownMemory { block, memory } => {
+                write!(f, "buffer {block} is in memory {memory}, which does not exist")
+            }
+            Self::DoesNotFit {
+                block,
+                memory,
+                bytes,
+                free,
+                largest_free,
+            } => write!(
+                f,
+                "buffer {block} of {bytes} bytes does not fit in memory {memory}, which has {free} bytes left of which at most {largest_free} together"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// Gives every block an address in its memory, in the order of `blocks`. The blocks of every
+/// memory are placed by decreasing size at the lowest address where the first token is a
+/// multiple of the alignment after the header. The padding this needs stays free for smaller
+/// blocks.
+pub fn layout<const N: usize>(
+    memories: &[Memory],
+    blocks: &[Block<N>],
+) -> Result<Vec<Placement>, LayoutError> {
+    let mut placements = blocks
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            memories.get(b.memory).ok_or(LayoutError::UnknownMemory {
+                block: i,
+                memory: b.memory,
+            })?;
+            Ok(Placement {
+                memory: b.memory,
+                address: 0,
+                bytes: b.layout.bytes(b.tokens),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (m, memory) in memories.iter().enumerate() {
+        // free address ranges, sorted
+        let mut free = vec![(memory.base, memory.base + memory.size)];
+        let mut order = (0..blocks.len())
+            .filter(|i| blocks[*i].memory == m)
+            .collect::<Vec<_>>();
+        order.sort_by_key(|i| std::cmp::Reverse(placements[*i].bytes));
+        for i in order {
+            let Block { layout, .. } = blocks[i];
+            let bytes = placements[i].bytes;
+            let alignment = layout.alignment.max(1);
+            let fit = free.iter().enumerate().find_map(|(f, (start, end))| {
+                let address = (start + layout.header).next_multiple_of(alignment) - layout.header;
+                (address + bytes <= *end).then_some((f, address))
+            });
+            let Some((f, address)) = fit else {
+                return Err(LayoutError::DoesNotFit {
+                    block: i,
+                    memory: memory.name.clone(),
+                    bytes,
+                    free: free.iter().map(|(s, e)| e - s).sum(),
+                    largest_free: free.iter().map(|(s, e)| e - s).max().unwrap_or(0),
+                });
+            };
+            let (start, end) = free.remove(f);
+            for (s, e) in [(address + bytes, end), (start, address)] {
+                if s < e {
+                    free.insert(f, (s, e));
+                }
+            }
+            placements[i].address = address;
+        }
+    }
+    Ok(placements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligned() {
+        let memories = [Memory {
+            name: "sram".to_string(),
+            base: 0x1000,
+            size: 64,
+        }];
+        let block = |token_size: usize, tokens: usize, alignment: usize, header: usize| Block {
+            tokens: [tokens].into(),
+            layout: TokenLayout {
+                token_size: [token_size].into(),
+                alignment,
+                header,
+            },
+            memory: 0,
+        };
+        let blocks = [block(8, 4, 8, 4), block(3, 2, 1, 0), block(4, 1, 4, 0)];
+        let placements = layout(&memories, &blocks).unwrap();
+        // the largest block leaves 4 bytes of padding before it, which the smallest one reuses
+        assert_eq!(
+            placements.iter().map(|p| p.address).collect::<Vec<_>>(),
+            [0x1004, 0x1028, 0x1000]
+        );
+        assert_eq!(
+            placements.iter().map(|p| p.bytes).collect::<Vec<_>>(),
+            [36, 6, 4]
+        );
+
+        let too_large = [block(8, 4, 8, 4), block(8, 4, 8, 0)];
+        assert_eq!(
+            layout(&memories, &too_large),
+            Err(LayoutError::DoesNotFit {
+                block: 1,
+                memory: "sram".to_string(),
+                bytes: 32,
+                free: 28,
+                largest_free: 24,
+            })
+        );
+        let nowhere = [Block {
+            memory: 1,
+            ..block(1, 1, 1, 0)
+        }];
+        assert_eq!(
+            layout(&memories, &nowhere),
+            Err(LayoutError::UnknownMemory {
+                block: 0,
+                memory: 1
+            })
+        );
+    }
+}
@@ -1,67 +1,185 @@
 #![feature(iterator_try_collect)]
 
+pub mod layout;
+pub mod lifetime;
+pub mod memory;
+pub mod pareto;
+
 use mdsdf::{util::bounded_iterator, vector::Vector, Channel, ChannelIndex};
-use milp_formulation::{ExecutionTimeT, MilpFormulation, NameT};
-use std::{borrow::Cow, collections::BTreeMap, isize};
+use milp_formulation::{
+    model::{self, Constr, Expr, Model, Var},
+    Dependency, ExecutionTimeT, MilpFormulation, NameT, Origin,
+};
+use std::{borrow::Cow, collections::BTreeMap, fmt, isize};
+
+/// Reason why [`BufferedMrsdf::add_buffer`] rejected a channel. The model is left unchanged,
+/// except for [`BufferError::Model`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum BufferError {
+    /// The channel does not belong to the graph of the formulation.
+    UnknownChannel(ChannelIndex),
+    /// The source of `channel` produces a different number of tokens per iteration than its
+    /// target consumes, or none at all, so its buffer would change the repetition vector.
+    RateMismatch {
+        channel: ChannelIndex,
+        dimension: usize,
+        produced: usize,
+        consumed: usize,
+    },
+    /// A firing of this actor has no offset in the formulation, which was built from another
+    /// graph.
+    MissingActor(usize),
+    /// The target of `channel` consumes tokens before its source produces them in the schedule,
+    /// which does not come from solving the formulation.
+    NegativeOccupancy {
+        channel: ChannelIndex,
+        dimension: usize,
+    },
+    Model(model::Error),
+}
+
+impl fmt::Display for BufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownChannel(channel) => {
+                write!(f, "channel {channel} does not belong to the graph")
+            }
+            Self::RateMismatch {
+                channel,
+                dimension,
+                produced,
+                consumed,
+            } => write!(
+                f,
+                "channel {channel} carries {produced} tokens per iteration from its source but {consumed} to its target in dimension {dimension}"
+            ),
+            Self::MissingActor(actor) => {
+                write!(f, "a firing of actor {actor} has no offset in the formulation")
+            }
+            Self::NegativeOccupancy { channel, dimension } => write!(
+                f,
+                "the schedule consumes tokens of channel {channel} before they are produced in dimension {dimension}"
+            ),
+            Self::Model(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BufferError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Model(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<model::Error> for BufferError {
+    fn from(e: model::Error) -> Self {
+        Self::Model(e)
+    }
+}
 
 pub struct BufferedMrsdf<'a, 'b: 'a, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>> {
     pub milp: &'a mut MilpFormulation<'b, N, ExecutionTime, Name>,
+    regions: Vec<memory::Region>,
 }
 
 impl<'a, 'b, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>
     BufferedMrsdf<'a, 'b, N, ExecutionTime, Name>
 {
     pub fn new(milp: &'a mut MilpFormulation<'b, N, ExecutionTime, Name>) -> Self {
-        Self { milp }
+        Self {
+            milp,
+            regions: Vec::new(),
+        }
     }
 
-    pub fn add_buffer(
-        &mut self,
+    /// Source and target of the channel in the opposite direction of `channel`, the initial
+    /// tokens of `channel` and its tokens per iteration, if a buffer can bound it.
+    fn check_buffer(
+        &self,
         channel: ChannelIndex,
-        buffer_size: Vector<N, grb::Expr>,
-    ) -> grb::Result<()> {
-        use grb::prelude::*;
-
-        let Channel {
-            production_rate: consumption_rate,
-            consumption_rate: production_rate,
+    ) -> Result<(usize, usize, Vector<N, isize>, Vector<N, usize>), BufferError> {
+        let hsdf = &self.milp.hsdf;
+        let (_, Channel {
             source: target,
             target: source,
-            .. } = self.milp.hsdf.mdsdf.get_channel(channel);
+            initial_tokens,
+            .. }) = hsdf
+            .mdsdf
+            .channels()
+            .find(|(c, _)| *c == channel)
+            .ok_or(BufferError::UnknownChannel(channel))?;
+        let (source, target, initial_tokens) = (*source, *target, *initial_tokens);
 
-        assert!(
-            self.milp.hsdf.repetition_vector[*source] * *production_rate
-                == self.milp.hsdf.repetition_vector[*target] * *consumption_rate,
-            "Repetition vector would change"
-        );
-        let model = &mut self.milp.model;
-        let denominator = self.milp.hsdf.repetition_vector[*source] * *production_rate;
-        let one_over_denominator = Vector::from([1.0; N]) / denominator.map(|e| e as f64);
+        let denominator = hsdf.tokens_per_iteration(channel);
+        let consumed = hsdf.consumed(channel, hsdf.repetition_vector[source]);
+        for d in 0..N {
+            if denominator[d] == 0 || denominator[d] != consumed[d] {
+                return Err(BufferError::RateMismatch {
+                    channel,
+                    dimension: d,
+                    produced: denominator[d],
+                    consumed: consumed[d],
+                });
+            }
+        }
+        for a in [source, target] {
+            if bounded_iterator(Cow::Borrowed(&hsdf.repetition_vector[a]))
+                .any(|i| !self.milp.u.contains_key(&(a, i)))
+            {
+                return Err(BufferError::MissingActor(a));
+            }
+        }
+        Ok((source, target, initial_tokens, denominator))
+    }
 
-        let mut memoized: [BTreeMap<usize, grb::Var>; N] = (0..N)
-            .map(|_| Default::default())
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap();
-        for index in bounded_iterator(Cow::Borrowed(&denominator)) {
-            for (d, tokens) in index.iter().enumerate() {
-                let v = add_intvar!(model, bounds: 0..)?;
-                memoized[d].insert(*tokens, v.clone());
-                let den = denominator[d];
-                let name = format!("(d+{tokens})/{den}");
-                let tokens = *tokens as f64;
-                model.add_constr(
-                    &name,
-                    c!(v <= (buffer_size[d].clone() + tokens) * one_over_denominator[d]),
+    /// Bounds the tokens in `channel` by `buffer_size` with a channel in the opposite direction.
+    /// The channel is checked against the formulation before anything is added to the model.
+    pub fn add_buffer(
+        &mut self,
+        channel: ChannelIndex,
+        buffer_size: Vector<N, Expr>,
+    ) -> Result<(), BufferError> {
+        let (source, target, initial_tokens, denominator) = self.check_buffer(channel)?;
+        let hsdf = &self.milp.hsdf;
+        let model = &mut self.milp.model;
+        let mut floors = (0..N)
+            .map(|d| {
+                Floors::new(
+                    model,
+                    format!("buffer_{channel}_{d}"),
+                    denominator[d],
+                    buffer_size[d].clone(),
+                )
+            })
+            .collect::<model::Result<Vec<_>>>()?;
+        // the initial tokens already occupy part of the buffer
+        for d in 0..N {
+            if initial_tokens[d] > 0 {
+                let constr = model.add_constr(
+                    &format!("initial_fill_{channel}_{d}"),
+                    buffer_size[d].clone().geq(initial_tokens[d] as f64),
                 )?;
+                self.milp.origins.insert(
+                    constr,
+                    Origin::BufferCapacity {
+                        channel,
+                        dimension: d,
+                    },
+                );
             }
         }
 
-        for si in bounded_iterator(Cow::Borrowed(&self.milp.hsdf.repetition_vector[*source])) {
-            for ti in bounded_iterator(Cow::Borrowed(&self.milp.hsdf.repetition_vector[*target])) {
-                let tokens = (*production_rate * si).map(|e| e as isize)
-                    - (*consumption_rate * ti).map(|e| e as isize)
-                    + (denominator.map(|e| e as isize) - consumption_rate.map(|e| e as isize));
+        for si in bounded_iterator(Cow::Borrowed(&hsdf.repetition_vector[source])) {
+            for ti in bounded_iterator(Cow::Borrowed(&hsdf.repetition_vector[target])) {
+                // space freed by the consumer before firing si, minus the space the producer
+                // needs up to and including firing ti and the space of the initial tokens
+                let tokens = hsdf.consumed(channel, si).map(|e| e as isize)
+                    - hsdf.produced(channel, ti + Vector::from([1; N])).map(|e| e as isize)
+                    - initial_tokens
+                    + denominator.map(|e| e as isize);
                 let to_floor_tokens = tokens
                     .modulo(denominator.map(|e| e as isize))
                     .map(|e| e as usize);
@@ -70,27 +188,166 @@ impl<'a, 'b, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>
                 let u = &self.milp.u;
                 let throughputs = &self.milp.throughputs;
                 let execution_time = &mut self.milp.execution_time;
-                let u_source = u.get(&(*source, si)).unwrap().clone();
-                let u_target = u.get(&(*target, ti)).unwrap().clone();
+                let name = &mut self.milp.name;
+                let u_source = *u.get(&(source, si)).unwrap();
+                let u_target = *u.get(&(target, ti)).unwrap();
                 for d in 0..N {
                     let tokens =
-                        memoized[d].get(&to_floor_tokens[d]).unwrap().clone() + hsdf_tokens[d];
+                        floors[d].floor(model, to_floor_tokens[d])? + hsdf_tokens[d] as f64;
                     let throuput = throughputs[d];
-                    let et = execution_time((*source, si));
-                    model.add_constr("", c!(u_target >= u_source + et * throuput - tokens))?;
+                    let et = execution_time((source, si)) as f64;
+                    let constr = model.add_constr(
+                        &format!(
+                            "buffer_{channel}_{d}_{}_{}",
+                            name((source, si)),
+                            name((target, ti))
+                        ),
+                        u_target.geq(u_source + et * throuput - tokens),
+                    )?;
+                    self.milp.origins.insert(
+                        constr,
+                        Origin::Dependency(Dependency {
+                            channel,
+                            source: (source, si),
+                            target: (target, ti),
+                            dimension: d,
+                            buffer: true,
+                        }),
+                    );
                 }
             }
         }
 
+        for (d, floors) in floors.into_iter().enumerate() {
+            for constr in floors.constrs {
+                self.milp.origins.insert(
+                    constr,
+                    Origin::BufferCapacity {
+                        channel,
+                        dimension: d,
+                    },
+                );
+            }
+        }
+
         Ok(())
     }
 }
 
+/// Tokens of the dependencies of one dimension of a buffer, `floor((B + r) / den)` for its size
+/// `B`, the tokens `den` of the channel per iteration and a residue `r` below `den`.
+///
+/// The integer part of `B` is `den * quotient + s`, with `s` below `den` given by its binary
+/// digits. The floor is then `quotient`, plus one if `s >= den - r`. That holds when the digits
+/// of `s` agree with those of `den - r` down to one where `s` has a one and `den - r` a zero, or
+/// agree everywhere, so it is a sum of indicators of prefixes of the digits of `s`, which are
+/// exclusive. Every prefix gets a continuous variable that can only be one if `s` starts with
+/// it. Dependencies only get looser with more tokens, so the solver raises them to the floor
+/// anyway, and the buffer needs one integer variable per digit instead of one per residue.
+struct Floors {
+    name: String,
+    denominator: usize,
+    quotient: Var,
+    /// Digits of `s`, the least significant first.
+    digits: Vec<Var>,
+    /// Indicator of every prefix of the digits of `s` used so far, by length and value.
+    prefixes: BTreeMap<(usize, usize), Var>,
+    constrs: Vec<Constr>,
+}
+
+impl Floors {
+    fn new(
+        model: &mut Model,
+        name: String,
+        denominator: usize,
+        buffer_size: Expr,
+    ) -> model::Result<Self> {
+        let n_digits = (usize::BITS - (denominator - 1).leading_zeros()) as usize;
+        let quotient = model.add_intvar(&format!("{name}_quotient"), 0.0, f64::INFINITY)?;
+        let digits = (0..n_digits)
+            .map(|i| model.add_intvar(&format!("{name}_digit_{i}"), 0.0, 1.0))
+            .collect::<model::Result<Vec<_>>>()?;
+        let remainder = digits
+            .iter()
+            .enumerate()
+            .map(|(i, digit)| *digit * (1 << i) as f64)
+            .sum::<Expr>();
+
+        let mut constrs = vec![model.add_constr(
+            &format!("{name}_size"),
+            (quotient * denominator as f64 + remainder.clone()).leq(buffer_size),
+        )?];
+        if !digits.is_empty() {
+            constrs.push(model.add_constr(
+                &format!("{name}_remainder"),
+                remainder.leq((denominator - 1) as f64),
+            )?);
+        }
+        Ok(Self {
+            name,
+            denominator,
+            quotient,
+            digits,
+            prefixes: BTreeMap::new(),
+            constrs,
+        })
+    }
+
+    /// Indicator of `s` starting with the `length` most significant digits of `value`.
+    fn prefix(&mut self, model: &mut Model, length: usize, value: usize) -> model::Result<Expr> {
+        if length == 0 {
+            return Ok(1.0.into());
+        }
+        if let Some(prefix) = self.prefixes.get(&(length, value)) {
+            return Ok((*prefix).into());
+        }
+        let parent = self.prefix(model, length - 1, value >> 1)?;
+        let digit = self.digits[self.digits.len() - length];
+        let literal = match value & 1 {
+            1 => Expr::from(digit),
+            _ => Expr::from(1.0) - digit,
+        };
+        let name = &self.name;
+        let prefix = model.add_ctsvar(&format!("{name}_prefix_{length}_{value}"), 0.0, 1.0)?;
+        self.constrs.push(model.add_constr(
+            &format!("{name}_prefix_{length}_{value}_parent"),
+            prefix.leq(parent),
+        )?);
+        self.constrs.push(model.add_constr(
+            &format!("{name}_prefix_{length}_{value}_digit"),
+            prefix.leq(literal),
+        )?);
+        self.prefixes.insert((length, value), prefix);
+        Ok(prefix.into())
+    }
+
+    /// `floor((B + residue) / den)`.
+    fn floor(&mut self, model: &mut Model, residue: usize) -> model::Result<Expr> {
+        let mut floor = Expr::from(self.quotient);
+        if residue == 0 {
+            return Ok(floor);
+        }
+        let threshold = self.denominator - residue;
+        let n_digits = self.digits.len();
+        floor += self.prefix(model, n_digits, threshold)?;
+        for i in 0..n_digits {
+            if threshold >> i & 1 == 0 {
+                // agrees above digit i, where s has a one and the threshold a zero
+                floor += self.prefix(model, n_digits - i, (threshold >> i) | 1)?;
+            }
+        }
+        Ok(floor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use grb::prelude::*;
     use mdsdf::Mdsdf;
+    use milp_formulation::{
+        model::ModelSense,
+        solver::{DefaultSolver, Status},
+    };
     #[test]
     fn test() {
         let sizes = (7, 3);
@@ -149,8 +406,8 @@ mod tests {
         let mut buffered = BufferedMrsdf::new(&mut milp);
 
         let model = &mut buffered.milp.model;
-        let buffer1 = add_ctsvar!(model, name: "buffer1", bounds: 0..sizes.0).unwrap();
-        let buffer2 = add_ctsvar!(model, name: "buffer2", bounds: 0..sizes.1).unwrap();
+        let buffer1 = model.add_ctsvar("buffer1", 0.0, sizes.0 as f64).unwrap();
+        let buffer2 = model.add_ctsvar("buffer2", 0.0, sizes.1 as f64).unwrap();
         buffered
             .add_buffer(channel1, [buffer1.into()].into())
             .unwrap();
@@ -159,11 +416,202 @@ mod tests {
             .unwrap();
 
         let model = &mut buffered.milp.model;
-        let throughput = &buffered.milp.throughputs[0];
+        let throughput = buffered.milp.throughputs[0];
         model
-            .set_objective(throughput, grb::ModelSense::Maximize)
+            .set_objective(throughput, ModelSense::Maximize)
             .unwrap();
-        model.optimize().unwrap();
+        let solution = model.optimize::<DefaultSolver>().unwrap();
+        assert_eq!(solution.status, Status::Optimal);
+        let bottleneck = buffered.milp.bottleneck(&solution, 0).unwrap();
+        assert_eq!(bottleneck.actors(), vec![1]);
+    }
+
+    #[test]
+    fn too_small_buffer() {
+        let mut sdf = Mdsdf::new(2);
+        for a in 0..2 {
+            sdf.add_channel(Channel {
+                production_rate: [1].into(),
+                consumption_rate: [1].into(),
+                source: a,
+                target: a,
+                initial_tokens: [1].into(),
+            });
+        }
+        let channel = sdf.add_channel(Channel {
+            production_rate: [2].into(),
+            consumption_rate: [3].into(),
+            source: 0,
+            target: 1,
+            initial_tokens: [0].into(),
+        });
+
+        let hsdf = sdf.hsdf();
+        let mut milp = MilpFormulation::new(
+            Cow::Borrowed(&hsdf),
+            |_| 1,
+            |(a, i): (usize, Vector<1, usize>)| format!("{}({})", ["a", "b"][a], i[0]),
+        )
+        .unwrap();
+        let throughput = milp.throughputs[0];
+        milp.model
+            .add_constr("min_throughput", throughput.geq(0.1))
+            .unwrap();
+        let mut buffered = BufferedMrsdf::new(&mut milp);
+        let buffer = buffered.milp.model.add_ctsvar("buffer", 0.0, 3.0).unwrap();
+        buffered.add_buffer(channel, [buffer.into()].into()).unwrap();
+
+        let conflict = milp.diagnose::<DefaultSolver>().unwrap().unwrap();
+        assert_eq!(conflict.constraints, vec!["min_throughput"]);
+        assert_eq!(conflict.upper_bounds, vec!["buffer"]);
+        assert!(conflict.origins.iter().any(|o| matches!(
+            o,
+            Origin::BufferCapacity { channel: c, .. } if *c == channel
+        )));
+    }
+
+    #[test]
+    fn errors() {
+        let mut sdf = Mdsdf::<1>::new(2);
+        let channel = sdf.add_channel(Channel {
+            production_rate: [2].into(),
+            consumption_rate: [3].into(),
+            source: 0,
+            target: 1,
+            initial_tokens: [0].into(),
+        });
+        let mut other = sdf.clone();
+        let unknown = other.add_channel(Channel {
+            production_rate: [1].into(),
+            consumption_rate: [1].into(),
+            source: 1,
+            target: 0,
+            initial_tokens: [1].into(),
+        });
+
+        let mut hsdf = sdf.hsdf();
+        hsdf.repetition_vector[1] = [4].into();
+        let mut milp = MilpFormulation::new(
+            Cow::Borrowed(&hsdf),
+            |_| 1,
+            |(a, i): (usize, Vector<1, usize>)| format!("{}({})", ["a", "b"][a], i[0]),
+        )
+        .unwrap();
+        let mut buffered = BufferedMrsdf::new(&mut milp);
+        let buffer = buffered.milp.model.add_ctsvar("buffer", 0.0, 10.0).unwrap();
+        let vars = buffered.milp.model.vars().count();
+
+        assert_eq!(
+            buffered.add_buffer(unknown, [buffer.into()].into()),
+            Err(BufferError::UnknownChannel(unknown))
+        );
+        assert_eq!(
+            buffered.add_buffer(channel, [buffer.into()].into()),
+            Err(BufferError::RateMismatch {
+                channel,
+                dimension: 0,
+                produced: 6,
+                consumed: 12,
+            })
+        );
+        assert_eq!(buffered.milp.model.vars().count(), vars);
+    }
+
+    #[test]
+    fn initial_tokens() {
+        let mut sdf = Mdsdf::<1>::new(2);
+        let channel = sdf.add_channel(Channel {
+            production_rate: [1].into(),
+            consumption_rate: [1].into(),
+            source: 0,
+            target: 1,
+            initial_tokens: [2].into(),
+        });
+
+        let hsdf = sdf.hsdf();
+        let mut milp = MilpFormulation::new(
+            Cow::Borrowed(&hsdf),
+            |_| 1,
+            |(a, i): (usize, Vector<1, usize>)| format!("{}({})", ["a", "b"][a], i[0]),
+        )
+        .unwrap();
+        let mut buffered = BufferedMrsdf::new(&mut milp);
+        let buffer = buffered.milp.model.add_ctsvar("buffer", 0.0, 2.0).unwrap();
+        buffered.add_buffer(channel, [buffer.into()].into()).unwrap();
+
+        // a full buffer of two tokens lets both actors work on two iterations at once
+        let throughput = milp.throughputs[0];
+        milp.model
+            .set_objective(throughput, ModelSense::Maximize)
+            .unwrap();
+        let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+        assert!((solution.value(throughput).unwrap() - 1.0).abs() < 1e-6);
+
+        milp.model.set_bounds(buffer, 0.0, 1.0).unwrap();
+        let conflict = milp.diagnose::<DefaultSolver>().unwrap().unwrap();
+        assert_eq!(conflict.upper_bounds, vec!["buffer"]);
+        assert_eq!(
+            conflict.origins,
+            vec![Origin::BufferCapacity {
+                channel,
+                dimension: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn matches_reverse_channel() {
+        use num::ToPrimitive;
+
+        // a buffer of a fixed size behaves like a channel back with the free space on it
+        for (production_rate, consumption_rate, initial_tokens) in [(2, 3, 0), (3, 5, 1), (4, 1, 2)]
+        {
+            let min_size = production_rate + consumption_rate + initial_tokens
+                - num::integer::gcd(production_rate, consumption_rate);
+            for size in min_size..min_size + 8 {
+                let mut sdf = Mdsdf::<1>::new(2);
+                let channel = sdf.add_channel(Channel {
+                    production_rate: [production_rate].into(),
+                    consumption_rate: [consumption_rate].into(),
+                    source: 0,
+                    target: 1,
+                    initial_tokens: [initial_tokens as isize].into(),
+                });
+                let execution_time = |(a, _): (usize, Vector<1, usize>)| [2, 3][a];
+
+                let mut reverse = sdf.clone();
+                reverse.add_channel(Channel {
+                    production_rate: [consumption_rate].into(),
+                    consumption_rate: [production_rate].into(),
+                    source: 1,
+                    target: 0,
+                    initial_tokens: [(size - initial_tokens) as isize].into(),
+                });
+                let period = reverse.hsdf().maximum_cycle_ratio(execution_time).unwrap()[0].period;
+
+                let hsdf = sdf.hsdf();
+                let mut milp = MilpFormulation::new(
+                    Cow::Borrowed(&hsdf),
+                    execution_time,
+                    |(a, i)| format!("{}({})", ["a", "b"][a], i[0]),
+                )
+                .unwrap();
+                let mut buffered = BufferedMrsdf::new(&mut milp);
+                let buffer = buffered
+                    .milp
+                    .model
+                    .add_ctsvar("buffer", 0.0, size as f64 + 0.5)
+                    .unwrap();
+                buffered.add_buffer(channel, [buffer.into()].into()).unwrap();
+                let throughput = milp.throughputs[0];
+                milp.model
+                    .set_objective(throughput, ModelSense::Maximize)
+                    .unwrap();
+                let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+                let throughput = solution.value(throughput).unwrap();
+                assert!((throughput * period.to_f64().unwrap() - 1.0).abs() < 1e-6);
+            }
+        }
     }
 
     /*#[test]
@@ -0,0 +1,324 @@
+use crate::{memory::TokenLayout, BufferError, BufferedMrsdf};
+use mdsdf::{util::bounded_iterator, vector::Vector, ChannelIndex};
+use milp_formulation::{schedule::PeriodicSchedule, ExecutionTimeT, NameT};
+use std::{borrow::Cow, collections::BTreeMap};
+
+/// Resolution of the times of a [`Lifetime`], as a fraction of the period. Events closer than
+/// this happen at the same time, so that a buffer emptied when another one is filled does not
+/// overlap it because of rounding in the solver.
+const RESOLUTION: f64 = 1e-9;
+
+/// Tokens in the buffer of a channel over one period of a [`PeriodicSchedule`], see
+/// [`BufferedMrsdf::lifetime`]. A firing of the source claims the space of its tokens when it
+/// starts, and a firing of the target frees it when it ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lifetime {
+    pub channel: ChannelIndex,
+    pub period: f64,
+    /// Time within the period of every change of the tokens in the buffer, and the tokens
+    /// after it, sorted by time. The tokens before the first change are those after the last.
+    pub occupancy: Vec<(f64, usize)>,
+    /// Bytes between two tokens, see [`TokenLayout::stride`].
+    pub token_bytes: usize,
+    /// Bytes of the buffer besides its tokens, taken over the whole period.
+    pub header: usize,
+}
+
+impl Lifetime {
+    /// Tokens in the buffer at `time`, or right after it if they change then.
+    pub fn tokens_at(&self, time: f64) -> usize {
+        let time = time.rem_euclid(self.period) + RESOLUTION * self.period;
+        let after = self.occupancy.partition_point(|(t, _)| *t <= time);
+        match after {
+            0 => self.occupancy.last(),
+            _ => self.occupancy.get(after - 1),
+        }
+        .map_or(0, |(_, tokens)| *tokens)
+    }
+
+    /// Bytes the buffer takes at `time`, including its header.
+    pub fn bytes_at(&self, time: f64) -> usize {
+        self.header + self.token_bytes * self.tokens_at(time)
+    }
+
+    /// Most tokens in the buffer at any time.
+    pub fn peak(&self) -> usize {
+        self.occupancy.iter().map(|(_, t)| *t).max().unwrap_or(0)
+    }
+
+    /// Bytes the tokens of the buffer need when they are given a fixed place in memory.
+    pub fn token_bytes(&self) -> usize {
+        self.token_bytes * self.peak()
+    }
+
+    /// Bytes the buffer needs when it is given a fixed place in memory.
+    pub fn bytes(&self) -> usize {
+        self.header + self.token_bytes()
+    }
+
+    /// Whether both buffers hold tokens at the same time.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.occupancy
+            .iter()
+            .chain(other.occupancy.iter())
+            .any(|(t, _)| self.tokens_at(*t) > 0 && other.tokens_at(*t) > 0)
+    }
+}
+
+/// Placement of buffers in a memory pool they share, see [`share`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedMemory {
+    /// Offset of the header of every buffer in the pool, in the order of the lifetimes.
+    pub headers: Vec<usize>,
+    /// Offset of the first token of every buffer in the pool, in the order of the lifetimes.
+    pub offsets: Vec<usize>,
+    /// Bytes of the pool.
+    pub size: usize,
+    /// Most bytes held by the buffers at any time, a lower bound on `size`.
+    pub peak: usize,
+}
+
+/// Places the buffers of `lifetimes`, which come from the same schedule, in one memory pool.
+/// The headers are kept for the whole period, one after the other at the start of the pool.
+/// The tokens follow them and are placed by decreasing [`Lifetime::token_bytes`] at the lowest
+/// offset where they do not overlap those of a buffer placed before them that holds tokens at
+/// the same time, so buffers that are never filled together reuse the same bytes.
+pub fn share(lifetimes: &[Lifetime]) -> SharedMemory {
+    let headers = lifetimes
+        .iter()
+        .scan(0, |offset, l| {
+            *offset += l.header;
+            Some(*offset - l.header)
+        })
+        .collect::<Vec<_>>();
+    let header_bytes = lifetimes.iter().map(|l| l.header).sum::<usize>();
+
+    let mut order = (0..lifetimes.len()).collect::<Vec<_>>();
+    order.sort_by_key(|i| std::cmp::Reverse(lifetimes[*i].token_bytes()));
+
+    let mut offsets = vec![header_bytes; lifetimes.len()];
+    let mut placed: Vec<usize> = Vec::new();
+    for i in order {
+        let bytes = lifetimes[i].token_bytes();
+        let mut taken = placed
+            .iter()
+            .filter(|j| lifetimes[i].overlaps(&lifetimes[**j]))
+            .map(|j| (offsets[*j], offsets[*j] + lifetimes[*j].token_bytes()))
+            .collect::<Vec<_>>();
+        taken.sort();
+        let mut offset = header_bytes;
+        for (start, end) in taken {
+            if offset + bytes <= start {
+                break;
+            }
+            offset = offset.max(end);
+        }
+        offsets[i] = offset;
+        placed.push(i);
+    }
+
+    let size = lifetimes
+        .iter()
+        .zip(offsets.iter())
+        .map(|(l, o)| o + l.token_bytes())
+        .max()
+        .unwrap_or(header_bytes);
+    let peak = lifetimes
+        .iter()
+        .flat_map(|l| l.occupancy.iter())
+        .map(|(t, _)| lifetimes.iter().map(|l| l.bytes_at(*t)).sum::<usize>())
+        .max()
+        .unwrap_or(0);
+    SharedMemory {
+        headers,
+        offsets,
+        size,
+        peak,
+    }
+}
+
+impl<'a, 'b, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>
+    BufferedMrsdf<'a, 'b, N, ExecutionTime, Name>
+{
+    /// Lifetime of the tokens of `channel` in `dimension` of `schedule`, which has to come from
+    /// solving `self.milp`, with bytes according to `layout`. The firings of the source with
+    /// the same index in `dimension` claim their space when the first of them starts, and those
+    /// of the target free it when the last of them ends.
+    pub fn lifetime(
+        &self,
+        schedule: &PeriodicSchedule<N>,
+        channel: ChannelIndex,
+        layout: TokenLayout<N>,
+        dimension: usize,
+    ) -> Result<Lifetime, BufferError> {
+        let (consumer, producer, initial_tokens, _) = self.check_buffer(channel)?;
+        let hsdf = &self.milp.hsdf;
+        let period = schedule.period[dimension];
+
+        // time and tokens of every claim and release, by index in `dimension`
+        let mut claims = BTreeMap::<usize, (f64, isize)>::new();
+        let mut releases = BTreeMap::<usize, (f64, isize)>::new();
+        for (actor, claim, events) in [
+            (producer, true, &mut claims),
+            (consumer, false, &mut releases),
+        ] {
+            for i in bounded_iterator(Cow::Borrowed(&hsdf.repetition_vector[actor])) {
+                let firing = schedule
+                    .actors
+                    .get(&(actor, i))
+                    .ok_or(BufferError::MissingActor(actor))?;
+                let next = i + Vector::from([1; N]);
+                let (time, tokens) = match claim {
+                    true => (
+                        firing.offset[dimension],
+                        hsdf.produced(channel, next)[dimension]
+                            - hsdf.produced(channel, i)[dimension],
+                    ),
+                    false => (
+                        firing.offset[dimension] + firing.execution_time as f64,
+                        hsdf.consumed(channel, next)[dimension]
+                            - hsdf.consumed(channel, i)[dimension],
+                    ),
+                };
+                let event = events
+                    .entry(i[dimension])
+                    .or_insert((time, tokens as isize));
+                event.0 = match claim {
+                    true => event.0.min(time),
+                    false => event.0.max(time),
+                };
+            }
+        }
+
+        // an event at `time` happens in every period from the one it falls in, so in a period
+        // long after the schedule starts, the tokens before it are those of the initial tokens
+        // less the events that fall in later periods
+        let mut tokens = initial_tokens[dimension];
+        let mut changes = BTreeMap::<u64, isize>::new();
+        for (events, sign) in [(claims, 1), (releases, -1)] {
+            for (time, amount) in events.into_values() {
+                let periods = time / period;
+                let mut shift = periods.floor();
+                let mut fraction = periods - shift;
+                if fraction > 1.0 - RESOLUTION {
+                    shift += 1.0;
+                    fraction = 0.0;
+                }
+                tokens -= sign * amount * shift as isize;
+                *changes
+                    .entry((fraction / RESOLUTION).round() as u64)
+                    .or_default() += sign * amount;
+            }
+        }
+        let occupancy = changes
+            .into_iter()
+            .map(|(fraction, change)| {
+                tokens += change;
+                let tokens = usize::try_from(tokens)
+                    .map_err(|_| BufferError::NegativeOccupancy { channel, dimension })?;
+                Ok((fraction as f64 * RESOLUTION * period, tokens))
+            })
+            .collect::<Result<_, BufferError>>()?;
+
+        Ok(Lifetime {
+            channel,
+            period,
+            occupancy,
+            token_bytes: layout.stride()[dimension],
+            header: layout.header,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdsdf::{Channel, Mdsdf};
+    use milp_formulation::{model::ModelSense, solver::DefaultSolver, MilpFormulation};
+
+    #[test]
+    fn disjoint() {
+        // a -> b and c -> d are never filled together, since c waits for b and a for d
+        let mut sdf = Mdsdf::<1>::new(4);
+        let channels =
+            [(0, 1, 0), (1, 2, 0), (2, 3, 0), (3, 0, 1)].map(|(source, target, initial_tokens)| {
+                sdf.add_channel(Channel {
+                    production_rate: [1].into(),
+                    consumption_rate: [1].into(),
+                    source,
+                    target,
+                    initial_tokens: [initial_tokens].into(),
+                })
+            });
+        let hsdf = sdf.hsdf();
+        let mut milp = MilpFormulation::new(
+            Cow::Borrowed(&hsdf),
+            |_: (usize, Vector<1, usize>)| 1,
+            |(a, i)| format!("{}({})", ["a", "b", "c", "d"][a], i[0]),
+        )
+        .unwrap();
+        let throughput = milp.throughputs[0];
+        milp.model
+            .set_objective(throughput, ModelSense::Maximize)
+            .unwrap();
+        let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+        let schedule = milp.schedule(&solution).unwrap();
+        assert_eq!(schedule.period[0], 4.0);
+
+        let buffered = BufferedMrsdf::new(&mut milp);
+        let layout = TokenLayout {
+            token_size: [3].into(),
+            alignment: 4,
+            header: 0,
+        };
+        let lifetimes =
+            [channels[0], channels[2]].map(|c| buffered.lifetime(&schedule, c, layout, 0).unwrap());
+        let first = schedule.actors[&(0, [0].into())].offset[0];
+        assert_eq!(lifetimes[0].tokens_at(first + 1.0), 1);
+        assert_eq!(lifetimes[0].tokens_at(first + 3.0), 0);
+        assert_eq!(lifetimes[1].tokens_at(first + 3.0), 1);
+        assert!(!lifetimes[0].overlaps(&lifetimes[1]));
+        assert_eq!(
+            share(&lifetimes),
+            SharedMemory {
+                headers: vec![0, 0],
+                offsets: vec![0, 0],
+                size: 4,
+                peak: 4,
+            }
+        );
+
+        // headers stay reserved while the tokens of both buffers reuse the same bytes
+        let headed = lifetimes.clone().map(|l| Lifetime { header: 8, ..l });
+        assert_eq!(headed[0].bytes_at(first + 3.0), 8);
+        assert_eq!(
+            share(&headed),
+            SharedMemory {
+                headers: vec![0, 8],
+                offsets: vec![16, 16],
+                size: 20,
+                peak: 20,
+            }
+        );
+
+        // the initial token of d -> a is still in its buffer while a fires
+        let back = buffered
+            .lifetime(&schedule, channels[3], layout, 0)
+            .unwrap();
+        assert!(back.overlaps(&lifetimes[0]));
+        let shared = share(&[lifetimes[0].clone(), back]);
+        assert_eq!(shared.size, 8);
+        assert_eq!(shared.peak, 8);
+
+        // b cannot end before a starts
+        let mut early = schedule.clone();
+        early.actors.get_mut(&(1, [0].into())).unwrap().offset[0] = first - 2.0;
+        assert_eq!(
+            buffered.lifetime(&early, channels[0], layout, 0),
+            Err(BufferError::NegativeOccupancy {
+                channel: channels[0],
+                dimension: 0,
+            })
+        );
+    }
+}
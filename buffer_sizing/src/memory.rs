@@ -0,0 +1,222 @@
+use crate::{BufferError, BufferedMrsdf};
+use mdsdf::{vector::Vector, ChannelIndex};
+use milp_formulation::{
+    model::{self, Constr, Expr, Var},
+    solver::Solution,
+    ExecutionTimeT, NameT,
+};
+
+/// Bytes a buffer takes in memory for its tokens. Tokens are laid out one after the other, each
+/// padded to `alignment`, after a header of `header` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenLayout<const N: usize> {
+    /// Bytes of a token in every dimension. A dimension whose tokens the memory does not hold,
+    /// e.g. the rows of a buffer that only keeps a few lines of an image, takes zero bytes.
+    pub token_size: Vector<N, usize>,
+    /// Alignment of every token, in bytes. Zero or one leaves the tokens unpadded.
+    pub alignment: usize,
+    /// Bytes the buffer needs besides its tokens, e.g. for its read and write pointers.
+    pub header: usize,
+}
+
+impl<const N: usize> TokenLayout<N> {
+    /// Unpadded tokens of `token_size` bytes without a header.
+    pub fn new(token_size: Vector<N, usize>) -> Self {
+        Self {
+            token_size,
+            alignment: 1,
+            header: 0,
+        }
+    }
+
+    /// Bytes between the starts of two consecutive tokens in every dimension.
+    pub fn stride(&self) -> Vector<N, usize> {
+        let alignment = self.alignment.max(1);
+        self.token_size.map(|s| s.div_ceil(alignment) * alignment)
+    }
+
+    /// Bytes of a buffer holding `tokens` in every dimension.
+    pub fn bytes(&self, tokens: Vector<N, usize>) -> usize {
+        let stride = self.stride();
+        self.header
+            + stride
+                .iter()
+                .zip(tokens.iter())
+                .map(|(s, t)| s * t)
+                .sum::<usize>()
+    }
+
+    /// Bytes of a buffer of `buffer_size` tokens, as an expression of the model.
+    pub fn bytes_expr(&self, buffer_size: &Vector<N, Expr>) -> Expr {
+        self.stride()
+            .iter()
+            .zip(buffer_size.iter())
+            .map(|(s, b)| b.clone() * *s as f64)
+            .sum::<Expr>()
+            + self.header as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RegionIndex(usize);
+
+/// Memory holding buffers, e.g. the SRAM of a tile, see [`BufferedMrsdf::add_region`].
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub name: String,
+    /// Bytes available, or `None` if the region is unbounded.
+    pub size: Option<usize>,
+    /// Bytes used by the buffers allocated so far, bounded by `size`.
+    pub usage: Var,
+    bytes: Expr,
+    constr: Option<Constr>,
+}
+
+impl<'a, 'b, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>
+    BufferedMrsdf<'a, 'b, N, ExecutionTime, Name>
+{
+    /// Adds a memory region of `size` bytes, whose usage is the variable `memory_{name}`. An
+    /// infeasible model then reports the upper bound of that variable when the buffers do not
+    /// fit.
+    pub fn add_region(&mut self, name: &str, size: Option<usize>) -> model::Result<RegionIndex> {
+        let usage = self.milp.model.add_ctsvar(
+            &format!("memory_{name}"),
+            0.0,
+            size.map_or(f64::INFINITY, |s| s as f64),
+        )?;
+        self.regions.push(Region {
+            name: name.to_string(),
+            size,
+            usage,
+            bytes: 0.0.into(),
+            constr: None,
+        });
+        Ok(RegionIndex(self.regions.len() - 1))
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// Counts `bytes` in the usage of `region`, e.g. those of a buffer shared by several
+    /// channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `region` was added to another [`BufferedMrsdf`].
+    pub fn allocate(&mut self, region: RegionIndex, bytes: Expr) -> model::Result<()> {
+        let model = &mut self.milp.model;
+        let Region {
+            name,
+            usage,
+            bytes: allocated,
+            constr,
+            ..
+        } = &mut self.regions[region.0];
+        *allocated += bytes;
+        if let Some(constr) = constr.take() {
+            model.remove(constr)?;
+        }
+        *constr = Some(model.add_constr(
+            &format!("memory_{name}_usage"),
+            usage.equals(allocated.clone()),
+        )?);
+        Ok(())
+    }
+
+    /// Adds a buffer to `channel` whose size is an integer variable `buffer_{channel}_{d}` in
+    /// every dimension, and allocates its bytes according to `layout` in `region`. Returns the
+    /// size variables.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `region` was added to another [`BufferedMrsdf`].
+    pub fn add_sized_buffer(
+        &mut self,
+        channel: ChannelIndex,
+        layout: TokenLayout<N>,
+        region: RegionIndex,
+    ) -> Result<Vector<N, Var>, BufferError> {
+        assert!(region.0 < self.regions.len());
+        self.check_buffer(channel)?;
+        let mut sizes = Vec::with_capacity(N);
+        for d in 0..N {
+            sizes.push(self.milp.model.add_intvar(
+                &format!("buffer_{channel}_{d}"),
+                0.0,
+                f64::INFINITY,
+            )?);
+        }
+        let sizes = Vector::from(<[Var; N]>::try_from(sizes).unwrap());
+        let buffer_size = sizes.map(Expr::from);
+        self.allocate(region, layout.bytes_expr(&buffer_size))?;
+        self.add_buffer(channel, buffer_size)?;
+        Ok(sizes)
+    }
+
+    /// Bytes used in every region by `solution`, in the order the regions were added.
+    pub fn region_usage(&self, solution: &Solution) -> Option<Vec<usize>> {
+        self.regions
+            .iter()
+            .map(|r| solution.value(r.usage).map(|v| v.round() as usize))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdsdf::{Channel, Mdsdf};
+    use milp_formulation::{
+        model::ModelSense,
+        solver::{DefaultSolver, Status},
+        MilpFormulation,
+    };
+    use std::borrow::Cow;
+
+    #[test]
+    fn bytes() {
+        let layout = TokenLayout {
+            token_size: [3].into(),
+            alignment: 4,
+            header: 8,
+        };
+        assert_eq!(layout.stride(), [4].into());
+        assert_eq!(layout.bytes([5].into()), 28);
+
+        let mut sdf = Mdsdf::<1>::new(2);
+        let channel = sdf.add_channel(Channel {
+            production_rate: [2].into(),
+            consumption_rate: [3].into(),
+            source: 0,
+            target: 1,
+            initial_tokens: [0].into(),
+        });
+        let hsdf = sdf.hsdf();
+        // the smallest buffer without deadlock holds p + c - gcd(p, c) = 4 tokens
+        for (size, live) in [(24, true), (23, false)] {
+            let mut milp = MilpFormulation::new(
+                Cow::Borrowed(&hsdf),
+                |(a, _): (usize, Vector<1, usize>)| [1, 1][a],
+                |(a, i)| format!("{}({})", ["a", "b"][a], i[0]),
+            )
+            .unwrap();
+            let mut buffered = BufferedMrsdf::new(&mut milp);
+            let sram = buffered.add_region("sram", Some(size)).unwrap();
+            let buffer = buffered.add_sized_buffer(channel, layout, sram).unwrap();
+            let throughput = buffered.milp.throughputs[0];
+            buffered
+                .milp
+                .model
+                .set_objective(throughput, ModelSense::Maximize)
+                .unwrap();
+            let solution = buffered.milp.model.optimize::<DefaultSolver>().unwrap();
+            assert_eq!(solution.status, Status::Optimal);
+            assert_eq!(solution.value(throughput).unwrap() > 1e-6, live);
+            if live {
+                assert_eq!(solution.value(buffer[0]).map(f64::round), Some(4.0));
+                assert_eq!(buffered.region_usage(&solution), Some(vec![24]));
+            }
+        }
+    }
+}
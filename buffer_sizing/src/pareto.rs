@@ -0,0 +1,253 @@
+use crate::BufferedMrsdf;
+use milp_formulation::{
+    model::{Expr, ModelSense, Var},
+    solver::{Solver, Status},
+    ExecutionTimeT, NameT,
+};
+
+/// Trade-off between the period and the buffer sizes, see [`BufferedMrsdf::pareto`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParetoPoint {
+    /// Smallest period reachable with `sizes`, the inverse of the throughput.
+    pub period: f64,
+    /// Weighted sum of `sizes`.
+    pub capacity: f64,
+    /// Size of every buffer, in the order they were given.
+    pub sizes: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParetoOptions {
+    /// Dimension whose throughput is traded off.
+    pub dimension: usize,
+    /// Relative amount by which every point has to improve the period of the previous one, and
+    /// by which the capacity may exceed its minimum when the period is minimized. Sizes and
+    /// periods closer than that to an integer are rounded to it.
+    pub tolerance: f64,
+    /// Stops after this many points, keeping those with the smallest capacities.
+    pub max_points: Option<usize>,
+}
+
+impl Default for ParetoOptions {
+    fn default() -> Self {
+        Self {
+            dimension: 0,
+            tolerance: 1e-6,
+            max_points: None,
+        }
+    }
+}
+
+impl<'a, 'b, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>
+    BufferedMrsdf<'a, 'b, N, ExecutionTime, Name>
+{
+    /// Explores the Pareto front of the period against the weighted capacity of `buffers`,
+    /// from the smallest capacity that does not deadlock down to the smallest period. Every
+    /// step minimizes the capacity for a period below the one of the previous point, then
+    /// minimizes the period for that capacity. The model of `self.milp` is left untouched, so
+    /// constraints already in it, e.g. a minimum throughput, bound the exploration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffers` and `weights` differ in length or a weight is not finite.
+    pub fn pareto<S: Solver>(
+        &mut self,
+        buffers: &[Var],
+        weights: &[f64],
+        options: ParetoOptions,
+    ) -> Result<Vec<ParetoPoint>, S::Error> {
+        assert_eq!(buffers.len(), weights.len());
+        let ParetoOptions {
+            dimension,
+            tolerance,
+            max_points,
+        } = options;
+        let round = |x: f64, tolerance: f64| {
+            if (x - x.round()).abs() <= tolerance {
+                x.round()
+            } else {
+                x
+            }
+        };
+
+        let mut model = self.milp.model.clone();
+        let throughput = self.milp.throughputs[dimension];
+        let capacity = buffers
+            .iter()
+            .zip(weights)
+            .map(|(b, w)| *b * *w)
+            .sum::<Expr>();
+        // firing every actor after the other is always possible with large enough buffers, and
+        // actors that take no time can still fire once per time unit
+        let mut period = (self
+            .milp
+            .hsdf
+            .actors()
+            .map(&mut self.milp.execution_time)
+            .sum::<usize>() as f64)
+            .max(1.0);
+
+        let mut points: Vec<ParetoPoint> = Vec::new();
+        while max_points.is_none_or(|m| points.len() < m) {
+            let period_bound = model
+                .add_constr("max_period", (throughput * period).geq(1.0))
+                .unwrap();
+            model
+                .set_objective(capacity.clone(), ModelSense::Minimize)
+                .expect("weights have to be finite");
+            let solution = model.optimize::<S>()?;
+            let Some(min_capacity) = solution.objective() else {
+                break;
+            };
+            model.remove(period_bound).unwrap();
+
+            let slack = tolerance * min_capacity.abs().max(1.0);
+            let capacity_bound = model
+                .add_constr("max_capacity", capacity.clone().leq(min_capacity + slack))
+                .unwrap();
+            model
+                .set_objective(throughput, ModelSense::Maximize)
+                .unwrap();
+            let sized = solution;
+            let solution = model.optimize::<S>()?;
+            model.remove(capacity_bound).unwrap();
+            let (solution, t) = match solution.status {
+                Status::Optimal => match solution.value(throughput).filter(|t| *t > 0.0) {
+                    Some(t) => (solution, t),
+                    None => break,
+                },
+                // the capacity bound is feasible, so actors that take no time fire arbitrarily
+                // often with the sizes that met it
+                Status::Unbounded | Status::InfeasibleOrUnbounded => (sized, f64::INFINITY),
+                _ => break,
+            };
+
+            let sizes = buffers
+                .iter()
+                .map(|b| round(solution.value(*b).unwrap(), slack))
+                .collect::<Vec<_>>();
+            let point = ParetoPoint {
+                period: round(1.0 / t, tolerance),
+                capacity: round(sizes.iter().zip(weights).map(|(s, w)| s * w).sum(), slack),
+                sizes,
+            };
+            period = point.period * (1.0 - tolerance);
+            points.push(point);
+        }
+        Ok(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdsdf::{vector::Vector, Channel, Mdsdf};
+    use milp_formulation::{solver::DefaultSolver, MilpFormulation};
+    use std::borrow::Cow;
+
+    #[test]
+    fn front() {
+        let mut sdf = Mdsdf::new(3);
+        for a in 0..3 {
+            sdf.add_channel(Channel {
+                production_rate: [1].into(),
+                consumption_rate: [1].into(),
+                source: a,
+                target: a,
+                initial_tokens: [1].into(),
+            });
+        }
+        let channels = [(0, 1, 2, 3), (1, 2, 1, 2)].map(|(source, target, p, c)| {
+            sdf.add_channel(Channel {
+                production_rate: [p].into(),
+                consumption_rate: [c].into(),
+                source,
+                target,
+                initial_tokens: [0].into(),
+            })
+        });
+
+        let hsdf = sdf.hsdf();
+        let mut milp = MilpFormulation::new(
+            Cow::Borrowed(&hsdf),
+            |(a, _): (usize, Vector<1, usize>)| [1, 2, 2][a],
+            |(a, i)| format!("{}({})", ["a", "b", "c"][a], i[0]),
+        )
+        .unwrap();
+        let mut buffered = BufferedMrsdf::new(&mut milp);
+        let mut buffers = Vec::new();
+        for (i, channel) in channels.into_iter().enumerate() {
+            let buffer = buffered
+                .milp
+                .model
+                .add_ctsvar(&format!("buffer{i}"), 0.0, f64::INFINITY)
+                .unwrap();
+            buffered
+                .add_buffer(channel, [buffer.into()].into())
+                .unwrap();
+            buffers.push(buffer);
+        }
+
+        let points = buffered
+            .pareto::<DefaultSolver>(&buffers, &[1.0, 1.0], ParetoOptions::default())
+            .unwrap();
+        // the smallest buffers without deadlock are p + c - gcd(p, c), and b bounds the period
+        assert_eq!(points.first().unwrap().sizes, [4.0, 2.0]);
+        assert_eq!(points.last().unwrap().period, 4.0);
+        for pair in points.windows(2) {
+            assert!(pair[0].period > pair[1].period);
+            assert!(pair[0].capacity < pair[1].capacity);
+        }
+
+        let limited = ParetoOptions {
+            max_points: Some(1),
+            ..Default::default()
+        };
+        let first = buffered
+            .pareto::<DefaultSolver>(&buffers, &[1.0, 1.0], limited)
+            .unwrap();
+        assert_eq!(first, points[..1]);
+    }
+
+    #[test]
+    fn zero_execution_times() {
+        let mut sdf = Mdsdf::new(2);
+        let channel = sdf.add_channel(Channel {
+            production_rate: [2].into(),
+            consumption_rate: [3].into(),
+            source: 0,
+            target: 1,
+            initial_tokens: [3].into(),
+        });
+        let hsdf = sdf.hsdf();
+        let mut milp = MilpFormulation::new(
+            Cow::Borrowed(&hsdf),
+            |_: (usize, Vector<1, usize>)| 0,
+            |(a, i)| format!("{}({})", ["a", "b"][a], i[0]),
+        )
+        .unwrap();
+        let mut buffered = BufferedMrsdf::new(&mut milp);
+        let buffer = buffered
+            .milp
+            .model
+            .add_ctsvar("buffer", 0.0, f64::INFINITY)
+            .unwrap();
+        buffered
+            .add_buffer(channel, [buffer.into()].into())
+            .unwrap();
+
+        let points = buffered
+            .pareto::<DefaultSolver>(&[buffer], &[1.0], ParetoOptions::default())
+            .unwrap();
+        // actors that take no time free tokens as soon as they arrive, so the initial tokens
+        // bound the buffer at any throughput and the front is a single point
+        assert_eq!(
+            points,
+            [ParetoPoint {
+                period: 0.0,
+                capacity: 3.0,
+                sizes: vec![3.0],
+            }]
+        );
+    }
+}
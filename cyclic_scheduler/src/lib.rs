@@ -4,15 +4,14 @@ mod py;
 
 use itertools::Itertools;
 use mdsdf::vector::Vector;
-use milp_formulation::{ExecutionTimeT, MilpFormulation, NameT};
+use milp_formulation::{model, ExecutionTimeT, MilpFormulation, NameT, Origin};
 use std::collections::BTreeMap;
 
 pub fn cyclic_scheduler<const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>(
     milp: &mut MilpFormulation<'_, N, ExecutionTime, Name>,
     mut processor: impl FnMut((usize, Vector<N, usize>)) -> usize,
     dimension: usize,
-) -> grb::Result<()> {
-    use grb::prelude::*;
+) -> model::Result<()> {
     let mut processor_assignment: BTreeMap<usize, Vec<(usize, Vector<N, usize>)>> =
         Default::default();
     for k in milp.u.keys() {
@@ -24,38 +23,50 @@ pub fn cyclic_scheduler<const N: usize, ExecutionTime: ExecutionTimeT<N>, Name:
 
     let model = &mut milp.model;
     let throughput = milp.throughputs[dimension];
-    for tasks in processor_assignment.values() {
+    for (p, tasks) in processor_assignment.iter() {
         for (t1, t2) in tasks.iter().tuple_combinations() {
             let task1 = milp.u.get(t1).unwrap();
             let task2 = milp.u.get(t2).unwrap();
-            let e1 = (milp.execution_time)(*t1);
-            let e2 = (milp.execution_time)(*t2);
-            let k = add_intvar!(model, bounds: ..)?;
-            model.add_constr(
-                "",
-                c!(task1.clone() >= task2.clone() + throughput.clone() * e2 - k.clone()),
-            )?;
-            model.add_constr(
-                "",
-                c!(task2.clone() >= task1.clone() + throughput.clone() * e1 - (1 - k)),
-            )?;
+            let e1 = (milp.execution_time)(*t1) as f64;
+            let e2 = (milp.execution_time)(*t2) as f64;
+            let name = format!("exclusion_{p}_{}_{}", (milp.name)(*t1), (milp.name)(*t2));
+            let k = model.add_intvar(&format!("{name}_order"), f64::NEG_INFINITY, f64::INFINITY)?;
+            let origin = Origin::ProcessorExclusion {
+                processor: *p,
+                first: *t1,
+                second: *t2,
+            };
+            let constr = model.add_constr(&name, task1.geq(*task2 + throughput * e2 - k))?;
+            milp.origins.insert(constr, origin);
+            let constr = model.add_constr(&name, task2.geq(*task1 + throughput * e1 + k - 1.0))?;
+            milp.origins.insert(constr, origin);
         }
         if tasks.len() == 1 {
             let t = tasks.first().unwrap();
             let task = milp.u.get(t).unwrap();
-            let e = (milp.execution_time)(*t);
+            let e = (milp.execution_time)(*t) as f64;
             let throughput = milp.throughputs[dimension];
-            model.add_constr(
-                "",
-                c!(task.clone() >= task.clone() + throughput.clone() * e - 1),
+            let constr = model.add_constr(
+                &format!("exclusion_{p}_{}", (milp.name)(*t)),
+                task.geq(*task + throughput * e - 1.0),
             )?;
+            milp.origins.insert(
+                constr,
+                Origin::ProcessorExclusion {
+                    processor: *p,
+                    first: *t,
+                    second: *t,
+                },
+            );
         }
         let cycle_time = tasks
             .iter()
             .map(Clone::clone)
             .map(&mut milp.execution_time)
             .sum::<usize>() as f64;
-        model.add_constr("", c!(cycle_time * throughput.clone() <= 1))?;
+        let constr = model.add_constr(&format!("load_{p}"), (cycle_time * throughput).leq(1.0))?;
+        milp.origins
+            .insert(constr, Origin::ProcessorLoad { processor: *p });
     }
 
     Ok(())
@@ -64,10 +75,14 @@ pub fn cyclic_scheduler<const N: usize, ExecutionTime: ExecutionTimeT<N>, Name:
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::{borrow::Cow, collections::BTreeMap};
+    use std::{borrow::Cow, collections::BTreeMap, num::NonZeroUsize};
 
     use mdsdf::{vector::Vector, Channel, Mdsdf};
-    use milp_formulation::MilpFormulation;
+    use milp_formulation::{
+        model::ModelSense,
+        solver::{DefaultSolver, Status},
+        MilpFormulation,
+    };
 
     #[test]
     fn test() {
@@ -129,10 +144,47 @@ mod test {
         cyclic_scheduler(&mut milp, |(i, _)| [0, 1, 0][i], 0).unwrap();
 
         let model = &mut milp.model;
-        let throughput = &milp.throughputs[0];
+        let throughput = milp.throughputs[0];
         model
-            .set_objective(throughput, grb::ModelSense::Maximize)
+            .set_objective(throughput, ModelSense::Maximize)
+            .unwrap();
+        let solution = model.optimize::<DefaultSolver>().unwrap();
+        assert_eq!(solution.status, Status::Optimal);
+    }
+
+    #[test]
+    fn k_periodic() {
+        // a and c share a processor, b and d the other one, and d -> a holds two tokens
+        let mut sdf = Mdsdf::new(4);
+        for (source, target, initial_tokens) in [(0, 1, 0), (1, 2, 0), (2, 3, 0), (3, 0, 2)] {
+            sdf.add_channel(Channel {
+                production_rate: [1].into(),
+                consumption_rate: [1].into(),
+                source,
+                target,
+                initial_tokens: [initial_tokens].into(),
+            });
+        }
+        let hsdf = sdf.hsdf();
+
+        let throughput = |periodicity: usize| {
+            let mut milp = MilpFormulation::k_periodic(
+                Cow::Borrowed(&hsdf),
+                |_| NonZeroUsize::new(periodicity).unwrap(),
+                |(a, _): (usize, Vector<1, usize>)| [1, 3, 3, 3][a],
+                |(a, i)| format!("{}({})", ["a", "b", "c", "d"][a], i[0]),
+            )
             .unwrap();
-        model.optimize().unwrap();
+            cyclic_scheduler(&mut milp, |(a, _)| [0, 1, 0, 1][a], 0).unwrap();
+            milp.model
+                .set_objective(milp.throughputs[0], ModelSense::Maximize)
+                .unwrap();
+            let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+            solution.value(milp.throughputs[0]).unwrap() * milp.iterations as f64
+        };
+        // a strictly periodic schedule cannot interleave the processors, while alternating
+        // the offsets of two iterations reaches the load of b and d
+        assert!((throughput(1) - 1.0 / 7.0).abs() < 1e-6);
+        assert!((throughput(2) - 1.0 / 6.0).abs() < 1e-6);
     }
 }
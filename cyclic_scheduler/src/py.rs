@@ -1,5 +1,14 @@
+use buffer_sizing::{
+    layout::{self, Block},
+    memory::TokenLayout,
+};
 use mdsdf::{vector::Vector, Channel};
-use pyo3::{prelude::*, types::PyDict};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use std::path::PathBuf;
+
+fn value_error(e: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
 
 #[derive(Clone, Default)]
 struct Task {
@@ -12,6 +21,7 @@ struct Task {
 #[derive(Clone, Default)]
 struct Memory {
     size: usize,
+    base: usize,
 }
 
 #[derive(Clone, Default)]
@@ -26,10 +36,10 @@ struct MemoryIndex(usize);
 #[pyclass(name = "RingBuffer")]
 struct RingBufferIndex(usize);
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 struct RingBuffer {
     memory: MemoryIndex,
-    cost: Vector<2, f64>,
+    layout: TokenLayout<2>,
 }
 
 #[derive(Clone, Default)]
@@ -86,26 +96,45 @@ impl CyclicScheduler {
         ))
     }
 
-    fn add_ring_buffer(&mut self, cost: Vector<2, f64>, memory: MemoryIndex) -> RingBufferIndex {
+    /// `token_size` is in bytes per token in each dimension, and `header` in bytes per ring
+    /// buffer.
+    fn add_ring_buffer(
+        &mut self,
+        token_size: Vector<2, usize>,
+        memory: MemoryIndex,
+        alignment: Option<usize>,
+        header: Option<usize>,
+    ) -> RingBufferIndex {
         let result = self.ring_buffers.len();
         self.ring_buffers.push(RingBuffer {
             memory,
-            cost: cost.into(),
+            layout: TokenLayout {
+                token_size,
+                alignment: alignment.unwrap_or(1),
+                header: header.unwrap_or(0),
+            },
         });
         RingBufferIndex(result)
     }
 
-    fn add_memory(&mut self, memory_size: usize) -> MemoryIndex {
+    /// `base` is the address of the first byte of the memory, zero by default.
+    fn add_memory(&mut self, memory_size: usize, base: Option<usize>) -> MemoryIndex {
         let result = self.memories.len();
         self.memories.push(Memory {
             size: memory_size,
+            base: base.unwrap_or(0),
         });
         MemoryIndex(result)
     }
 
-    fn solve(&self) -> CyclicSchedulerSolution {
+    /// Writes the model to `model_path` before solving it if given, in the LP or MPS format
+    /// depending on its extension.
+    fn solve(&self, model_path: Option<PathBuf>) -> PyResult<CyclicSchedulerSolution> {
         use buffer_sizing;
-        use grb::prelude::*;
+        use milp_formulation::{
+            model::{Expr, ModelSense, Var},
+            solver::{DefaultSolver, Status},
+        };
         use std::borrow::Cow;
         let mut sdf = mdsdf::Mdsdf::<2>::new(self.tasks.len());
 
@@ -117,96 +146,130 @@ impl CyclicScheduler {
                 b.clone().map(|rbi| (ci, rbi))
             }).collect::<Vec<_>>();
 
-        let hsdf = sdf.hsdf();
+        let hsdf = sdf.try_hsdf().map_err(value_error)?;
         let mut milp = milp_formulation::MilpFormulation::new(
             Cow::Borrowed(&hsdf),
             |(i, _)| self.tasks[i].execution_time,
             |(i, _)| self.tasks[i].name.clone(),
         )
-        .unwrap();
-
-        let ring_buffer: Vec<Vector<2, Expr>> = (0..self.ring_buffers.len())
-            .map({
-                let model = &mut milp.model;
-                |i| {
-                    Ok::<Vector<2, grb::Expr>, grb::Error>(
-                        (
-                            add_ctsvar!(model, name: &format!("ring_buffer_{i}_0"), bounds: 0..)?
-                                + 0.0,
-                            add_ctsvar!(model, name: &format!("ring_buffer_{i}_1"), bounds: 0..)?
-                                + 0.0,
-                        )
-                            .into(),
-                    )
-                }
-            })
-            .try_collect()
-            .unwrap();
-        for (i, Memory { size, .. }) in self.memories.iter().enumerate() {
-            let allocation = self
-                .ring_buffers
-                .iter()
-                .zip(ring_buffer.iter())
-                .filter_map(
-                    |(
-                        RingBuffer {
-                            memory: MemoryIndex(j),
-                            cost,
-                        },
-                        v,
-                    )| (*j == i).then(|| *cost * v.clone()),
-                )
-                .fold(
-                    (Expr::from(0.0), Expr::from(0.0)).into(),
-                    |a: Vector<2, Expr>, b: Vector<2, Expr>| a + b,
-                );
-            milp.model
-                .add_constr(
-                    &format!("memory_allocation_{i}"),
-                    c!(allocation[0].clone() + allocation[1].clone() <= size),
-                )
-                .unwrap();
-        }
+        .map_err(value_error)?;
 
         let mut buffered_sdf = buffer_sizing::BufferedMrsdf::new(&mut milp);
+        let regions = self
+            .memories
+            .iter()
+            .enumerate()
+            .map(|(i, Memory { size, .. })| buffered_sdf.add_region(&i.to_string(), Some(*size)))
+            .try_collect::<Vec<_>>()
+            .map_err(value_error)?;
+        let mut ring_buffer: Vec<Vector<2, Var>> = Vec::new();
+        for (i, RingBuffer { memory, layout }) in self.ring_buffers.iter().enumerate() {
+            let region = *regions
+                .get(memory.0)
+                .ok_or_else(|| value_error(format!("ring buffer {i} is in an unknown memory")))?;
+            let model = &mut buffered_sdf.milp.model;
+            let size: Vector<2, Var> = (
+                model
+                    .add_intvar(&format!("ring_buffer_{i}_0"), 0.0, f64::INFINITY)
+                    .map_err(value_error)?,
+                model
+                    .add_intvar(&format!("ring_buffer_{i}_1"), 0.0, f64::INFINITY)
+                    .map_err(value_error)?,
+            )
+                .into();
+            buffered_sdf
+                .allocate(region, layout.bytes_expr(&size.map(Expr::from)))
+                .map_err(value_error)?;
+            ring_buffer.push(size);
+        }
 
         for (
             channel_index,
             RingBufferIndex(i),
         ) in buffered_channels
         {
+            let size = ring_buffer.get(i).ok_or_else(|| {
+                value_error(format!(
+                    "channel {channel_index} uses an unknown ring buffer"
+                ))
+            })?;
             buffered_sdf
-                .add_buffer(channel_index, ring_buffer[i].clone().into())
-                .unwrap()
+                .add_buffer(channel_index, size.map(Expr::from))
+                .map_err(value_error)?;
         }
 
         crate::cyclic_scheduler(&mut buffered_sdf.milp, |(i, _)| self.tasks[i].processor, 0)
-            .unwrap();
+            .map_err(value_error)?;
 
         //let buffered_sdf = buffer_sizing::BufferedMrsdf::new(&mut milp);
 
-        buffered_sdf.milp.model.write("solution.lp").unwrap();
+        if let Some(path) = model_path {
+            buffered_sdf.milp.model.write(path)?;
+        }
         buffered_sdf
             .milp
             .model
-            .set_objective(buffered_sdf.milp.throughputs[0], grb::ModelSense::Maximize)
-            .unwrap();
-        buffered_sdf.milp.model.optimize().unwrap();
-        buffered_sdf.milp.model.write("solution.sol").unwrap();
-        let throughput = buffered_sdf
+            .set_objective(buffered_sdf.milp.throughputs[0], ModelSense::Maximize)
+            .map_err(value_error)?;
+        let solution = buffered_sdf
             .milp
             .model
-            .get_obj_attr(attr::X, &buffered_sdf.milp.throughputs[0])
-            .unwrap();
-        CyclicSchedulerSolution {
-            throughput,
-            tasks: buffered_sdf
+            .optimize::<DefaultSolver>()
+            .map_err(value_error)?;
+        if matches!(
+            solution.status,
+            Status::Infeasible | Status::InfeasibleOrUnbounded
+        ) {
+            let conflict = buffered_sdf
                 .milp
-                .u
+                .diagnose::<DefaultSolver>()
+                .map_err(|e| {
+                    PyValueError::new_err(format!("the schedule is infeasible, diagnosing it failed: {e}"))
+                })?
+                .ok_or_else(|| {
+                    PyValueError::new_err(
+                        "the solver found the schedule infeasible or unbounded, but no conflict was found",
+                    )
+                })?;
+            return Err(PyValueError::new_err(format!(
+                "the schedule is infeasible because of:\n{conflict}"
+            )));
+        }
+        let schedule = buffered_sdf
+            .milp
+            .schedule(&solution)
+            .ok_or_else(|| PyValueError::new_err("the schedule has no positive throughput"))?;
+        let memories = self
+            .memories
+            .iter()
+            .enumerate()
+            .map(|(i, Memory { size, base })| layout::Memory {
+                name: i.to_string(),
+                base: *base,
+                size: *size,
+            })
+            .collect::<Vec<_>>();
+        let blocks = self
+            .ring_buffers
+            .iter()
+            .zip(ring_buffer.iter())
+            .map(|(RingBuffer { memory, layout }, size)| Block {
+                tokens: size.map(|v| solution.value(v).unwrap().round() as usize),
+                layout: *layout,
+                memory: memory.0,
+            })
+            .collect::<Vec<_>>();
+        let placements = layout::layout(&memories, &blocks).map_err(value_error)?;
+        Ok(CyclicSchedulerSolution {
+            throughput: schedule.throughput()[0],
+            memory_usage: buffered_sdf
+                .region_usage(&solution)
+                .ok_or_else(|| value_error("the solution has no memory usage"))?,
+            ring_buffer_addresses: placements.iter().map(|p| p.address).collect(),
+            tasks: schedule
+                .actors
                 .iter()
-                .map(|(a, b)| {
-                    let start_time =
-                        buffered_sdf.milp.model.get_obj_attr(attr::X, &b).unwrap() / throughput;
+                .map(|(a, actor)| {
                     let Task {
                         name,
                         color,
@@ -214,7 +277,7 @@ impl CyclicScheduler {
                         processor,
                     } = self.tasks[a.0].clone();
                     TaskSolution {
-                        start_time,
+                        start_time: actor.offset[0],
                         execution_time: execution_time as f64,
                         name,
                         color,
@@ -222,7 +285,7 @@ impl CyclicScheduler {
                     }
                 })
                 .collect(),
-        }
+        })
     }
 }
 
@@ -253,6 +316,12 @@ impl ToPyObject for TaskSolution {
 #[pyclass(name = "CyclicSchedulerSolution")]
 struct CyclicSchedulerSolution {
     throughput: f64,
+    /// Bytes used in every memory, in the order they were added.
+    #[pyo3(get)]
+    memory_usage: Vec<usize>,
+    /// Address of every ring buffer in its memory, in the order they were added.
+    #[pyo3(get)]
+    ring_buffer_addresses: Vec<usize>,
     tasks: Vec<TaskSolution>,
 }
 
@@ -264,8 +333,8 @@ impl CyclicSchedulerSolution {
 
         let data = PyDict::new_bound(py);
 
-        data.set_item("tasks", self.tasks.clone()).unwrap();
-        data.set_item("throughput", self.throughput).unwrap();
+        data.set_item("tasks", self.tasks.clone())?;
+        data.set_item("throughput", self.throughput)?;
         execute_js.call1((
             include_str!("../plotter/dist/bundle.js"),
             "cyclic_scheduler_plotter.main",
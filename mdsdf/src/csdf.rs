@@ -0,0 +1,324 @@
+use crate::vector::Vector;
+use crate::{Channel, ChannelIndex, Hsdf, InconsistencyError, Mdsdf};
+
+use std::borrow::Cow;
+
+/// Channel of a [`Csdf`] whose rates change with the phase of its actors. In every
+/// dimension, firing `i` of an actor with `p` phases is in phase `i % p` and uses the
+/// corresponding entry of the rates.
+#[derive(Debug, Clone)]
+pub struct CsdfChannel<const N: usize> {
+    /// Tokens produced in every phase of the source.
+    pub production_rates: Vec<Vector<N, usize>>,
+    /// Tokens consumed in every phase of the target.
+    pub consumption_rates: Vec<Vector<N, usize>>,
+    pub source: usize,
+    pub target: usize,
+    pub initial_tokens: Vector<N, isize>,
+}
+
+/// Cyclo-static dataflow graph: every actor cycles through a fixed number of phases, and the
+/// rates of its channels depend on the phase.
+#[derive(Debug, Clone, Default)]
+pub struct Csdf<const N: usize> {
+    phases: Vec<usize>,
+    channels: Vec<CsdfChannel<N>>,
+}
+
+/// Tokens transferred in the first `firings` firings of a port with the given phase rates.
+pub(crate) fn transferred<const N: usize>(
+    rates: &[Vector<N, usize>],
+    firings: Vector<N, usize>,
+) -> Vector<N, usize> {
+    let mut result = Vector::<N, usize>::default();
+    for d in 0..N {
+        let cycle = rates.iter().map(|r| r[d]).sum::<usize>();
+        let phase = firings[d] % rates.len();
+        result[d] =
+            firings[d] / rates.len() * cycle + rates[..phase].iter().map(|r| r[d]).sum::<usize>();
+    }
+    result
+}
+
+/// Firing of a port with the given phase rates that transfers token `token`, counting from
+/// zero. Every dimension has to transfer tokens in some phase.
+pub(crate) fn transferring<const N: usize>(
+    rates: &[Vector<N, usize>],
+    token: Vector<N, usize>,
+) -> Vector<N, usize> {
+    let mut result = Vector::<N, usize>::default();
+    for d in 0..N {
+        let cycle = rates.iter().map(|r| r[d]).sum::<usize>();
+        let mut remainder = token[d] % cycle;
+        let mut phase = 0;
+        while remainder >= rates[phase][d] {
+            remainder -= rates[phase][d];
+            phase += 1;
+        }
+        result[d] = token[d] / cycle * rates.len() + phase;
+    }
+    result
+}
+
+impl<const N: usize> Csdf<N> {
+    /// Graph without channels whose actors have the given number of phases, at least one each.
+    /// [`Csdf::try_hsdf`] rejects actors without phases.
+    pub fn new(phases: Vec<usize>) -> Self {
+        Self {
+            phases,
+            channels: Default::default(),
+        }
+    }
+
+    /// Adds `channel`, which needs one rate per phase of its source and target, as
+    /// [`Csdf::try_hsdf`] checks.
+    pub fn add_channel(&mut self, channel: CsdfChannel<N>) -> ChannelIndex {
+        let c = ChannelIndex(self.channels.len());
+        self.channels.push(channel);
+        c
+    }
+
+    pub fn get_channel(&self, ChannelIndex(i): ChannelIndex) -> &CsdfChannel<N> {
+        &self.channels[i]
+    }
+
+    pub fn n_actors(&self) -> usize {
+        self.phases.len()
+    }
+
+    pub fn phases(&self, actor: usize) -> usize {
+        self.phases[actor]
+    }
+
+    /// Channels in the order they were added.
+    pub fn channels(&self) -> impl Iterator<Item = (ChannelIndex, &CsdfChannel<N>)> + '_ {
+        self.channels
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (ChannelIndex(i), c))
+    }
+
+    /// SDF abstraction of the graph: every actor fires once per cycle through its phases and
+    /// its rates are summed over them.
+    pub fn sdf(&self) -> Mdsdf<N> {
+        let mut sdf = Mdsdf::new(self.n_actors());
+        for c in self.channels.iter() {
+            let sum = |rates: &[Vector<N, usize>]| {
+                rates
+                    .iter()
+                    .fold(Vector::<N, usize>::default(), |a, b| a + *b)
+            };
+            sdf.add_channel(Channel {
+                production_rate: sum(&c.production_rates),
+                consumption_rate: sum(&c.consumption_rates),
+                source: c.source,
+                target: c.target,
+                initial_tokens: c.initial_tokens,
+            });
+        }
+        sdf
+    }
+
+    pub fn hsdf(&self) -> Hsdf<'_, N> {
+        self.try_hsdf()
+            .unwrap_or_else(|e| panic!("csdf is not consistent: {}", e))
+    }
+
+    /// Expands the graph into one HSDF actor per firing. The repetition vector counts firings,
+    /// so it is the one of [`Csdf::sdf`] times the number of phases, and the phase of HSDF
+    /// actor `(a, i)` is [`Hsdf::phase`].
+    pub fn try_hsdf(&self) -> Result<Hsdf<'_, N>, InconsistencyError> {
+        if let Some(actor) = self.phases.iter().position(|p| *p == 0) {
+            return Err(InconsistencyError::NoPhases { actor });
+        }
+        for (c, channel) in self.channels() {
+            for (actor, rates) in [
+                (channel.source, &channel.production_rates),
+                (channel.target, &channel.consumption_rates),
+            ] {
+                if rates.len() != self.phases[actor] {
+                    return Err(InconsistencyError::PhaseCount {
+                        channel: c,
+                        actor,
+                        phases: self.phases[actor],
+                        rates: rates.len(),
+                    });
+                }
+            }
+        }
+        let sdf = self.sdf();
+        let Hsdf {
+            mut repetition_vector,
+            components,
+            ..
+        } = sdf.try_hsdf()?;
+        for (r, p) in repetition_vector.iter_mut().zip(self.phases.iter()) {
+            for (d, r) in r.iter_mut().enumerate() {
+                *r = r
+                    .checked_mul(*p)
+                    .ok_or(InconsistencyError::Overflow { dimension: d })?;
+            }
+        }
+        Ok(Hsdf {
+            repetition_vector,
+            components,
+            mdsdf: Cow::Owned(sdf),
+            csdf: Some(Cow::Borrowed(self)),
+        })
+    }
+
+    pub fn into_hsdf(self) -> Hsdf<'static, N> {
+        self.try_into_hsdf()
+            .unwrap_or_else(|e| panic!("csdf is not consistent: {}", e))
+    }
+
+    pub fn try_into_hsdf(self) -> Result<Hsdf<'static, N>, InconsistencyError> {
+        let Hsdf {
+            repetition_vector,
+            components,
+            mdsdf,
+            ..
+        } = self.try_hsdf()?;
+        let mdsdf = Cow::Owned(mdsdf.into_owned());
+        Ok(Hsdf {
+            repetition_vector,
+            components,
+            mdsdf,
+            csdf: Some(Cow::Owned(self)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::throughput::CriticalCycle;
+    use num::Rational64;
+
+    #[test]
+    fn single_phase() {
+        let channels = [(0, 1, 2, 3, 0), (1, 2, 1, 2, 0), (2, 0, 3, 1, 3)];
+        let mut sdf = Mdsdf::<1>::new(3);
+        let mut csdf = Csdf::<1>::new(vec![1; 3]);
+        for (source, target, production_rate, consumption_rate, initial_tokens) in channels {
+            sdf.add_channel(Channel {
+                production_rate: [production_rate].into(),
+                consumption_rate: [consumption_rate].into(),
+                source,
+                target,
+                initial_tokens: [initial_tokens].into(),
+            });
+            csdf.add_channel(CsdfChannel {
+                production_rates: vec![[production_rate].into()],
+                consumption_rates: vec![[consumption_rate].into()],
+                source,
+                target,
+                initial_tokens: [initial_tokens].into(),
+            });
+        }
+
+        let edges = |hsdf: &Hsdf<1>| {
+            hsdf.channels()
+                .map(|c| (c.source, c.target, c.initial_tokens))
+                .collect::<Vec<_>>()
+        };
+        let (expected, hsdf) = (sdf.hsdf(), csdf.hsdf());
+        assert_eq!(hsdf.repetition_vector, expected.repetition_vector);
+        assert_eq!(edges(&hsdf), edges(&expected));
+    }
+
+    #[test]
+    fn decoder() {
+        // a decoder that reads one token every three phases and writes one in each of them
+        let mut csdf = Csdf::<1>::new(vec![3, 1, 2]);
+        let c = csdf.add_channel(CsdfChannel {
+            production_rates: vec![[1].into()],
+            consumption_rates: vec![[1].into(), [0].into(), [0].into()],
+            source: 1,
+            target: 0,
+            initial_tokens: [0].into(),
+        });
+        csdf.add_channel(CsdfChannel {
+            production_rates: vec![[1].into(), [1].into(), [1].into()],
+            consumption_rates: vec![[2].into(), [1].into()],
+            source: 0,
+            target: 2,
+            initial_tokens: [0].into(),
+        });
+        csdf.add_channel(CsdfChannel {
+            production_rates: vec![[0].into(), [1].into()],
+            consumption_rates: vec![[1].into()],
+            source: 2,
+            target: 1,
+            initial_tokens: [1].into(),
+        });
+        for a in 0..3 {
+            csdf.add_channel(CsdfChannel {
+                production_rates: vec![[1].into(); csdf.phases(a)],
+                consumption_rates: vec![[1].into(); csdf.phases(a)],
+                source: a,
+                target: a,
+                initial_tokens: [1].into(),
+            });
+        }
+
+        let hsdf = csdf.hsdf();
+        assert_eq!(
+            hsdf.repetition_vector
+                .iter()
+                .map(|r| r[0])
+                .collect::<Vec<_>>(),
+            vec![3, 1, 2]
+        );
+        assert_eq!(hsdf.phase((0, [2].into())), [2].into());
+        assert_eq!(hsdf.tokens_per_iteration(c), [1].into());
+        assert_eq!(hsdf.consumed(c, [1].into()), [1].into());
+
+        // the first phase of the decoder waits for the source, which waits for the last
+        // phase of the sink
+        let edges = hsdf
+            .channels()
+            .filter(|e| e.source.0 != e.target.0)
+            .map(|e| (e.source, e.target, e.initial_tokens[0]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![
+                ((1, [0].into()), (0, [0].into()), 0),
+                ((0, [0].into()), (2, [0].into()), 0),
+                ((0, [1].into()), (2, [0].into()), 0),
+                ((0, [2].into()), (2, [1].into()), 0),
+                ((2, [1].into()), (1, [0].into()), 1),
+            ]
+        );
+
+        let execution_time = |(a, _): (usize, Vector<1, usize>)| [1, 2, 3][a];
+        let CriticalCycle { period, .. } = &hsdf.maximum_cycle_ratio(execution_time).unwrap()[0];
+        assert_eq!(*period, Rational64::from(10));
+    }
+
+    #[test]
+    fn phase_count() {
+        let mut csdf = Csdf::<1>::new(vec![2, 1]);
+        let c = csdf.add_channel(CsdfChannel {
+            production_rates: vec![[1].into()],
+            consumption_rates: vec![[2].into()],
+            source: 0,
+            target: 1,
+            initial_tokens: [0].into(),
+        });
+        assert_eq!(
+            csdf.try_hsdf().err(),
+            Some(InconsistencyError::PhaseCount {
+                channel: c,
+                actor: 0,
+                phases: 2,
+                rates: 1,
+            })
+        );
+        assert_eq!(
+            Csdf::<1>::new(vec![1, 0]).try_hsdf().err(),
+            Some(InconsistencyError::NoPhases { actor: 1 })
+        );
+    }
+}
@@ -1,10 +1,19 @@
+pub mod csdf;
+pub mod liveness;
 mod py;
+pub mod state_space;
+pub mod throughput;
 pub mod util;
 pub mod vector;
 
-use ndarray::Array2;
+use csdf::Csdf;
 use std::borrow::Cow;
-use util::{bounded_iterator, repetition_vector, BoundedIterator};
+use std::fmt;
+use std::slice;
+use util::{
+    bounded_iterator, connected_components, repetition_vector, BoundedIterator,
+    RepetitionVectorError,
+};
 use vector::Vector;
 
 #[derive(Debug, Clone)]
@@ -16,9 +25,81 @@ pub struct Channel<const N: usize> {
     pub initial_tokens: Vector<N, isize>,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ChannelIndex(usize);
 
+impl fmt::Display for ChannelIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Reason why an [`Mdsdf`] has no repetition vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InconsistencyError {
+    /// `channel` produces or consumes no tokens in `dimension`.
+    ZeroRate {
+        dimension: usize,
+        channel: ChannelIndex,
+    },
+    /// Going around `cycle`, the product of the production over consumption rates in
+    /// `dimension` is not one. Channels are listed in traversal order, which may go against
+    /// their direction.
+    Cycle {
+        dimension: usize,
+        cycle: Vec<ChannelIndex>,
+    },
+    /// The repetition vector of `dimension` does not fit in 64 bits.
+    Overflow { dimension: usize },
+    /// `actor` of a [`csdf::Csdf`] has no phases.
+    NoPhases { actor: usize },
+    /// `channel` of a [`csdf::Csdf`] gives `rates` rates for `actor`, which has `phases` phases.
+    PhaseCount {
+        channel: ChannelIndex,
+        actor: usize,
+        phases: usize,
+        rates: usize,
+    },
+}
+
+impl fmt::Display for InconsistencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroRate {
+                dimension,
+                channel: ChannelIndex(c),
+            } => write!(f, "channel {c} has a zero rate in dimension {dimension}"),
+            Self::Cycle { dimension, cycle } => {
+                let cycle = cycle
+                    .iter()
+                    .map(|ChannelIndex(c)| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(
+                    f,
+                    "channels [{cycle}] form a cycle with inconsistent rates in dimension {dimension}"
+                )
+            }
+            Self::Overflow { dimension } => write!(
+                f,
+                "repetition vector of dimension {dimension} does not fit in 64 bits"
+            ),
+            Self::NoPhases { actor } => write!(f, "actor {actor} has no phases"),
+            Self::PhaseCount {
+                channel: ChannelIndex(c),
+                actor,
+                phases,
+                rates,
+            } => write!(
+                f,
+                "channel {c} has {rates} rates for actor {actor}, which has {phases} phases"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InconsistencyError {}
+
 #[derive(Debug, Clone, Default)]
 pub struct Mdsdf<const N: usize> {
     n_actors: usize,
@@ -45,55 +126,106 @@ impl<const N: usize> Mdsdf<N> {
         &self.channels[i]
     }
 
+    pub fn n_actors(&self) -> usize {
+        self.n_actors
+    }
+
+    /// Channels in the order they were added.
+    pub fn channels(&self) -> impl Iterator<Item = (ChannelIndex, &Channel<N>)> + '_ {
+        self.channels
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (ChannelIndex(i), c))
+    }
+
+    /// Weakly connected components of the graph, see [`connected_components`].
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        connected_components(
+            self.n_actors,
+            self.channels.iter().map(|c| (c.source, c.target)),
+        )
+    }
+
     pub fn hsdf(&self) -> Hsdf<N> {
+        self.try_hsdf()
+            .unwrap_or_else(|e| panic!("sdf is not consistent: {}", e))
+    }
+
+    /// Expands the graph, normalising the repetition vector of every connected component on
+    /// its own.
+    pub fn try_hsdf(&self) -> Result<Hsdf<'_, N>, InconsistencyError> {
         let mut rv: Box<[Vector<N, usize>]> =
             vec![Vector::<N, usize>::default(); self.n_actors].into_boxed_slice();
         for d in 0..N {
-            let mut topology_matrix = Array2::<i32>::zeros((self.channels.len(), self.n_actors));
-            for (
-                Channel {
-                    production_rate,
-                    consumption_rate,
-                    source,
-                    target,
-                    ..
-                },
-                mut row,
-            ) in self.channels.iter().zip(topology_matrix.rows_mut())
-            {
-                row[*source] += production_rate[d] as i32;
-                row[*target] -= consumption_rate[d] as i32;
-            }
-
-            for (a, b) in rv
-                .iter_mut()
-                .zip(repetition_vector(&topology_matrix).expect("sdf is not consistent"))
-            {
+            for (a, b) in rv.iter_mut().zip(self.repetition_vector(d)?) {
                 a[d] = b;
             }
         }
 
-        Hsdf {
+        Ok(Hsdf {
             repetition_vector: rv,
+            components: self.components().into_boxed_slice(),
             mdsdf: Cow::Borrowed(self),
-        }
+            csdf: None,
+        })
     }
 
     pub fn into_hsdf(self) -> Hsdf<'static, N> {
+        self.try_into_hsdf()
+            .unwrap_or_else(|e| panic!("sdf is not consistent: {}", e))
+    }
+
+    pub fn try_into_hsdf(self) -> Result<Hsdf<'static, N>, InconsistencyError> {
         let Hsdf {
-            repetition_vector, ..
-        } = self.hsdf();
-        Hsdf {
             repetition_vector,
+            components,
+            ..
+        } = self.try_hsdf()?;
+        Ok(Hsdf {
+            repetition_vector,
+            components,
             mdsdf: Cow::Owned(self),
-        }
+            csdf: None,
+        })
+    }
+
+    /// Repetition vector of dimension `d`, see [`repetition_vector`].
+    fn repetition_vector(&self, d: usize) -> Result<Vec<usize>, InconsistencyError> {
+        let channels = self
+            .channels
+            .iter()
+            .map(|c| {
+                (
+                    c.source,
+                    c.target,
+                    c.production_rate[d],
+                    c.consumption_rate[d],
+                )
+            })
+            .collect::<Vec<_>>();
+        repetition_vector(self.n_actors, &channels).map_err(|e| match e {
+            RepetitionVectorError::ZeroRate(c) => InconsistencyError::ZeroRate {
+                dimension: d,
+                channel: ChannelIndex(c),
+            },
+            RepetitionVectorError::Inconsistent(cycle) => InconsistencyError::Cycle {
+                dimension: d,
+                cycle: cycle.into_iter().map(ChannelIndex).collect(),
+            },
+            RepetitionVectorError::Overflow => InconsistencyError::Overflow { dimension: d },
+        })
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Hsdf<'a, const N: usize> {
     pub repetition_vector: Box<[Vector<N, usize>]>,
+    /// Actors of every weakly connected component, see [`Mdsdf::components`].
+    pub components: Box<[Vec<usize>]>,
     pub mdsdf: Cow<'a, Mdsdf<N>>,
+    /// Cyclo-static graph this was expanded from, see [`Csdf::try_hsdf`]. `mdsdf` is then its
+    /// SDF abstraction and the rates of its channels only hold over a whole cycle of phases.
+    pub csdf: Option<Cow<'a, Csdf<N>>>,
 }
 
 impl<const N: usize> Hsdf<'_, N> {
@@ -105,10 +237,111 @@ impl<const N: usize> Hsdf<'_, N> {
         }
     }
 
-    pub fn channels(&self) -> HsdfChannels<'_, N, impl Iterator<Item = Channel<N>> + '_> {
-        let channels = self.mdsdf.channels.iter().map(Clone::clone);
+    pub fn channels(
+        &self,
+    ) -> HsdfChannels<'_, N, impl Iterator<Item = (ChannelIndex, Channel<N>)> + '_> {
+        let channels = self
+            .mdsdf
+            .channels
+            .iter()
+            .map(Clone::clone)
+            .enumerate()
+            .map(|(i, c)| (ChannelIndex(i), c));
         HsdfChannels::new(self, channels)
     }
+
+    /// Production and consumption rates of every phase of `channel`.
+    fn phase_rates(&self, channel: ChannelIndex) -> (&[Vector<N, usize>], &[Vector<N, usize>]) {
+        match &self.csdf {
+            Some(csdf) => {
+                let c = csdf.get_channel(channel);
+                (&c.production_rates, &c.consumption_rates)
+            }
+            None => {
+                let c = self.mdsdf.get_channel(channel);
+                (
+                    slice::from_ref(&c.production_rate),
+                    slice::from_ref(&c.consumption_rate),
+                )
+            }
+        }
+    }
+
+    /// Phase of `actor` in every dimension, always zero for an [`Mdsdf`].
+    pub fn phase(&self, (a, i): (usize, Vector<N, usize>)) -> Vector<N, usize> {
+        match &self.csdf {
+            Some(csdf) => i.map(|i| i % csdf.phases(a)),
+            None => Vector::default(),
+        }
+    }
+
+    /// Tokens that the source of `channel` produces on it in its firings before `firing`.
+    pub fn produced(&self, channel: ChannelIndex, firing: Vector<N, usize>) -> Vector<N, usize> {
+        csdf::transferred(self.phase_rates(channel).0, firing)
+    }
+
+    /// Tokens that the target of `channel` consumes from it in its firings before `firing`.
+    pub fn consumed(&self, channel: ChannelIndex, firing: Vector<N, usize>) -> Vector<N, usize> {
+        csdf::transferred(self.phase_rates(channel).1, firing)
+    }
+
+    /// Tokens that `channel` carries in one iteration.
+    pub fn tokens_per_iteration(&self, channel: ChannelIndex) -> Vector<N, usize> {
+        let source = self.mdsdf.get_channel(channel).source;
+        self.produced(channel, self.repetition_vector[source])
+    }
+
+    /// Actors of the `component`th connected component, see [`Hsdf::actors`].
+    pub fn component_actors(
+        &self,
+        component: usize,
+    ) -> impl Iterator<Item = (usize, Vector<N, usize>)> + '_ {
+        self.components[component].iter().flat_map(move |a| {
+            bounded_iterator(Cow::Borrowed(&self.repetition_vector[*a])).map(move |i| (*a, i))
+        })
+    }
+
+    /// Channels of the `component`th connected component, see [`Hsdf::channels`].
+    pub fn component_channels(
+        &self,
+        component: usize,
+    ) -> HsdfChannels<'_, N, impl Iterator<Item = (ChannelIndex, Channel<N>)> + '_> {
+        let actors = &self.components[component];
+        let channels = self
+            .mdsdf
+            .channels
+            .iter()
+            .map(Clone::clone)
+            .enumerate()
+            .filter(move |(_, c)| actors.binary_search(&c.source).is_ok())
+            .map(|(i, c)| (ChannelIndex(i), c));
+        HsdfChannels::new(self, channels)
+    }
+}
+
+impl<'a, const N: usize> Hsdf<'a, N> {
+    pub fn unfold(&self, iterations: usize) -> Self {
+        self.try_unfold(iterations)
+            .unwrap_or_else(|e| panic!("hsdf cannot be unfolded: {}", e))
+    }
+
+    /// Expands `iterations` iterations of the graph into a single one, so that firing `i` of
+    /// an actor in iteration `j` becomes its firing `i + j * r`, with `r` its entry of the
+    /// repetition vector. Initial tokens of the result count these longer iterations.
+    pub fn try_unfold(&self, iterations: usize) -> Result<Self, InconsistencyError> {
+        let mut repetition_vector = self.repetition_vector.clone();
+        for r in repetition_vector.iter_mut() {
+            for (d, r) in r.iter_mut().enumerate() {
+                *r = r
+                    .checked_mul(iterations)
+                    .ok_or(InconsistencyError::Overflow { dimension: d })?;
+            }
+        }
+        Ok(Self {
+            repetition_vector,
+            ..self.clone()
+        })
+    }
 }
 
 pub struct HsdfActors<'a, const N: usize> {
@@ -141,6 +374,8 @@ impl<const N: usize> Iterator for HsdfActors<'_, N> {
 }
 
 pub struct HsdfChannel<const N: usize> {
+    /// Channel of the [`Mdsdf`] this channel was expanded from.
+    pub channel: ChannelIndex,
     pub source: (usize, Vector<N, usize>),
     pub target: (usize, Vector<N, usize>),
     pub initial_tokens: Vector<N, isize>,
@@ -148,9 +383,9 @@ pub struct HsdfChannel<const N: usize> {
 
 pub struct HsdfChannels<'a, const N: usize, Channels>
 where
-    Channels: Iterator<Item = Channel<N>>,
+    Channels: Iterator<Item = (ChannelIndex, Channel<N>)>,
 {
-    pub current_channel: Option<Channel<N>>,
+    pub current_channel: Option<(ChannelIndex, Channel<N>)>,
     pub channels: Channels,
     pub hsdf: &'a Hsdf<'a, N>,
     pub bounded_iterator: BoundedIterator<'static, N>,
@@ -158,7 +393,7 @@ where
 
 impl<'a, const N: usize, Channels> HsdfChannels<'a, N, Channels>
 where
-    Channels: Iterator<Item = Channel<N>>,
+    Channels: Iterator<Item = (ChannelIndex, Channel<N>)>,
 {
     fn new(hsdf: &'a Hsdf<'a, N>, channels: Channels) -> Self {
         let mut result = Self {
@@ -173,10 +408,9 @@ where
 
     fn iterate_channel(&mut self) {
         self.current_channel = self.channels.next();
-        self.bounded_iterator = if let Some(current_channel) = &self.current_channel {
+        self.bounded_iterator = if let Some((channel, _)) = &self.current_channel {
             bounded_iterator(Cow::Owned::<Vector<N, usize>>(
-                self.hsdf.repetition_vector[current_channel.source]
-                    * current_channel.production_rate,
+                self.hsdf.tokens_per_iteration(*channel),
             ))
         } else {
             return;
@@ -186,33 +420,151 @@ where
 
 impl<'a, const N: usize, Channels> Iterator for HsdfChannels<'a, N, Channels>
 where
-    Channels: Iterator<Item = Channel<N>>,
+    Channels: Iterator<Item = (ChannelIndex, Channel<N>)>,
 {
     type Item = HsdfChannel<N>;
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let current_channel = self.current_channel.as_mut()?;
+            let (channel, current_channel) = self.current_channel.as_mut()?;
             let Some(index) = self.bounded_iterator.next() else {
                 self.iterate_channel();
                 continue;
             };
-            let index = index.map(|e| e as isize);
-            let tokens = index.clone() + current_channel.initial_tokens;
-            let production_rate = current_channel.production_rate.map(|e| e as isize);
-            let consumption_rate = current_channel.consumption_rate.map(|e| e as isize);
-            let rotation = self.hsdf.repetition_vector[current_channel.target].map(|e| e as isize)
-                * consumption_rate.clone();
-            let target_index =
-                (tokens.clone().modulo(rotation.clone())).flooring_div(consumption_rate);
-            let source_index = index.flooring_div(production_rate);
+            let (production_rates, consumption_rates) = self.hsdf.phase_rates(*channel);
+            let tokens = index.map(|e| e as isize) + current_channel.initial_tokens;
+            let rotation = self.hsdf.tokens_per_iteration(*channel).map(|e| e as isize);
+            let target_index = csdf::transferring(
+                consumption_rates,
+                tokens.modulo(rotation).map(|e| e as usize),
+            );
+            let source_index = csdf::transferring(production_rates, index);
             let initial_tokens = tokens.flooring_div(rotation);
 
             return Some(HsdfChannel {
-                source: (current_channel.source, source_index.map(|e| e as usize)),
-                target: (current_channel.target, target_index.map(|e| e as usize)),
+                channel: *channel,
+                source: (current_channel.source, source_index),
+                target: (current_channel.target, target_index),
                 initial_tokens,
             });
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inconsistent() {
+        let mut sdf = Mdsdf::<2>::new(3);
+        sdf.add_channel(Channel {
+            production_rate: [2, 1].into(),
+            consumption_rate: [1, 1].into(),
+            source: 0,
+            target: 1,
+            initial_tokens: [0, 0].into(),
+        });
+        sdf.add_channel(Channel {
+            production_rate: [1, 2].into(),
+            consumption_rate: [2, 1].into(),
+            source: 1,
+            target: 2,
+            initial_tokens: [0, 0].into(),
+        });
+        let c = sdf.add_channel(Channel {
+            production_rate: [1, 1].into(),
+            consumption_rate: [1, 1].into(),
+            source: 2,
+            target: 0,
+            initial_tokens: [1, 1].into(),
+        });
+
+        assert_eq!(
+            sdf.try_hsdf().unwrap_err(),
+            InconsistencyError::Cycle {
+                dimension: 1,
+                cycle: vec![ChannelIndex(0), ChannelIndex(1), ChannelIndex(2)],
+            }
+        );
+
+        sdf.channels[c.0].production_rate = [1, 0].into();
+        assert_eq!(
+            sdf.try_hsdf().unwrap_err(),
+            InconsistencyError::ZeroRate {
+                dimension: 1,
+                channel: c
+            }
+        );
+    }
+
+    #[test]
+    fn disconnected() {
+        let mut sdf = Mdsdf::<1>::new(5);
+        sdf.add_channel(Channel {
+            production_rate: [2].into(),
+            consumption_rate: [3].into(),
+            source: 0,
+            target: 2,
+            initial_tokens: [0].into(),
+        });
+        sdf.add_channel(Channel {
+            production_rate: [1].into(),
+            consumption_rate: [2].into(),
+            source: 3,
+            target: 1,
+            initial_tokens: [0].into(),
+        });
+
+        let hsdf = sdf.try_hsdf().unwrap();
+        assert_eq!(
+            hsdf.repetition_vector
+                .iter()
+                .map(|r| r[0])
+                .collect::<Vec<_>>(),
+            vec![3, 1, 2, 2, 1]
+        );
+        assert_eq!(&*hsdf.components, &[vec![0, 2], vec![1, 3], vec![4]]);
+        assert_eq!(hsdf.component_actors(1).count(), 3);
+        assert_eq!(hsdf.component_channels(0).count(), 6);
+        assert_eq!(hsdf.component_channels(2).count(), 0);
+        assert_eq!(hsdf.actors().count(), 9);
+    }
+
+    #[test]
+    fn unfold() {
+        let mut sdf = Mdsdf::<1>::new(2);
+        sdf.add_channel(Channel {
+            production_rate: [2].into(),
+            consumption_rate: [1].into(),
+            source: 0,
+            target: 1,
+            initial_tokens: [1].into(),
+        });
+        sdf.add_channel(Channel {
+            production_rate: [1].into(),
+            consumption_rate: [1].into(),
+            source: 0,
+            target: 0,
+            initial_tokens: [1].into(),
+        });
+
+        let hsdf = sdf.hsdf().unfold(2);
+        assert_eq!(*hsdf.repetition_vector, [[2].into(), [4].into()]);
+        let edges = hsdf
+            .channels()
+            .map(|c| (c.source.1[0], c.target.1[0], c.initial_tokens[0]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![
+                (0, 1, 0),
+                (0, 2, 0),
+                (1, 3, 0),
+                (1, 0, 1),
+                (0, 1, 0),
+                (1, 0, 1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use crate::vector::Vector;
+use crate::{ChannelIndex, HsdfChannel, InconsistencyError, Mdsdf};
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+/// Reason why an [`Mdsdf`] cannot complete an iteration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LivenessError<const N: usize> {
+    Inconsistent(InconsistencyError),
+    /// The HSDF actors in `blocked` never fire in the first iteration. `starving` lists the
+    /// channels on which they wait for tokens that never arrive.
+    Deadlock {
+        blocked: Vec<(usize, Vector<N, usize>)>,
+        starving: Vec<ChannelIndex>,
+    },
+}
+
+impl<const N: usize> fmt::Display for LivenessError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inconsistent(e) => e.fmt(f),
+            Self::Deadlock { blocked, starving } => {
+                let starving = starving
+                    .iter()
+                    .map(|ChannelIndex(c)| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(
+                    f,
+                    "actors {blocked:?} starve on channels [{starving}] in the first iteration"
+                )
+            }
+        }
+    }
+}
+
+impl<const N: usize> std::error::Error for LivenessError<N> {}
+
+impl<const N: usize> From<InconsistencyError> for LivenessError<N> {
+    fn from(e: InconsistencyError) -> Self {
+        Self::Inconsistent(e)
+    }
+}
+
+impl<const N: usize> Mdsdf<N> {
+    /// Symbolically executes one iteration of the repetition vector, firing every HSDF actor as
+    /// soon as the tokens it depends on are available. Returns the firing sequence.
+    ///
+    /// Token dependencies are resolved like [`crate::HsdfChannels`]: a dependency is met by
+    /// initial tokens when it refers to an earlier iteration, that is when no dimension is
+    /// negative and one is positive. Dependencies with a negative dimension refer to a later
+    /// iteration and are never met.
+    pub fn check_liveness(&self) -> Result<Vec<(usize, Vector<N, usize>)>, LivenessError<N>> {
+        let hsdf = self.try_hsdf()?;
+        let actors = hsdf.actors().collect::<Vec<_>>();
+        let indices: BTreeMap<(usize, Vector<N, usize>), usize> =
+            actors.iter().enumerate().map(|(i, a)| (*a, i)).collect();
+
+        let mut waiting = vec![0usize; actors.len()];
+        let mut dependents = vec![Vec::new(); actors.len()];
+        let mut dependencies = Vec::new();
+        for HsdfChannel {
+            channel,
+            source,
+            target,
+            initial_tokens,
+        } in hsdf.channels()
+        {
+            let (source, target) = (indices[&source], indices[&target]);
+            if initial_tokens.iter().any(|t| *t < 0) {
+                waiting[target] += 1;
+                dependencies.push((channel, None, target));
+            } else if initial_tokens.iter().all(|t| *t == 0) {
+                waiting[target] += 1;
+                dependents[source].push(target);
+                dependencies.push((channel, Some(source), target));
+            }
+        }
+
+        let mut fired = vec![false; actors.len()];
+        let mut sequence = Vec::with_capacity(actors.len());
+        let mut queue = (0..actors.len())
+            .filter(|a| waiting[*a] == 0)
+            .collect::<VecDeque<_>>();
+        while let Some(a) = queue.pop_front() {
+            fired[a] = true;
+            sequence.push(actors[a]);
+            for b in dependents[a].iter() {
+                waiting[*b] -= 1;
+                if waiting[*b] == 0 {
+                    queue.push_back(*b);
+                }
+            }
+        }
+
+        if sequence.len() == actors.len() {
+            return Ok(sequence);
+        }
+        let starving = dependencies
+            .into_iter()
+            .filter(|(_, source, target)| {
+                !fired[*target] && source.is_none_or(|source| !fired[source])
+            })
+            .map(|(channel, _, _)| channel)
+            .collect::<BTreeSet<_>>();
+        Err(LivenessError::Deadlock {
+            blocked: (0..actors.len())
+                .filter(|a| !fired[*a])
+                .map(|a| actors[a])
+                .collect(),
+            starving: starving.into_iter().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Channel;
+
+    #[test]
+    fn live() {
+        let mut sdf = Mdsdf::<1>::new(3);
+        for (source, target, production_rate, consumption_rate, initial_tokens) in
+            [(0, 1, 2, 3, 0), (1, 2, 1, 2, 0), (2, 0, 3, 1, 3)]
+        {
+            sdf.add_channel(Channel {
+                production_rate: [production_rate].into(),
+                consumption_rate: [consumption_rate].into(),
+                source,
+                target,
+                initial_tokens: [initial_tokens].into(),
+            });
+        }
+
+        let sequence = sdf.check_liveness().unwrap();
+        assert_eq!(
+            sequence,
+            vec![
+                (0, [0].into()),
+                (0, [1].into()),
+                (0, [2].into()),
+                (1, [0].into()),
+                (1, [1].into()),
+                (2, [0].into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn deadlock() {
+        let mut sdf = Mdsdf::<1>::new(2);
+        let c0 = sdf.add_channel(Channel {
+            production_rate: [2].into(),
+            consumption_rate: [1].into(),
+            source: 0,
+            target: 1,
+            initial_tokens: [0].into(),
+        });
+        let c1 = sdf.add_channel(Channel {
+            production_rate: [1].into(),
+            consumption_rate: [2].into(),
+            source: 1,
+            target: 0,
+            initial_tokens: [1].into(),
+        });
+
+        assert_eq!(
+            sdf.check_liveness(),
+            Err(LivenessError::Deadlock {
+                blocked: vec![(0, [0].into()), (1, [0].into()), (1, [1].into())],
+                starving: vec![c0, c1],
+            })
+        );
+    }
+
+    #[test]
+    fn multidimensional() {
+        let mut sdf = Mdsdf::<2>::new(2);
+        sdf.add_channel(Channel {
+            production_rate: [2, 1].into(),
+            consumption_rate: [1, 1].into(),
+            source: 0,
+            target: 1,
+            initial_tokens: [0, 0].into(),
+        });
+        let c = sdf.add_channel(Channel {
+            production_rate: [1, 1].into(),
+            consumption_rate: [2, 1].into(),
+            source: 1,
+            target: 0,
+            initial_tokens: [0, 1].into(),
+        });
+        assert_eq!(sdf.check_liveness().map(|s| s.len()), Ok(3));
+
+        sdf.channels[c.0].initial_tokens = [2, -1].into();
+        assert_eq!(
+            sdf.check_liveness(),
+            Err(LivenessError::Deadlock {
+                blocked: vec![(0, [0, 0].into()), (1, [0, 0].into()), (1, [1, 0].into())],
+                starving: vec![ChannelIndex(0), c],
+            })
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{Channel, Hsdf, HsdfChannel, Mdsdf};
-use pyo3::prelude::*;
+use pyo3::{exceptions::PyValueError, prelude::*};
 use std::borrow::Cow;
 
 #[pyclass(name = "Sdf")]
@@ -77,11 +77,15 @@ impl PySdf {
             .collect()
     }
 
-    fn hsdf(&self) -> PyHsdf {
-        PyHsdf {
+    fn hsdf(&self) -> PyResult<PyHsdf> {
+        Ok(PyHsdf {
             names: self.names.clone(),
-            hsdf: self.sdf.clone().into_hsdf(),
-        }
+            hsdf: self
+                .sdf
+                .clone()
+                .try_into_hsdf()
+                .map_err(|e| PyValueError::new_err(e.to_string()))?,
+        })
     }
 }
 
@@ -109,6 +113,7 @@ impl PyHsdf {
                      source: (s, si),
                      target: (t, ti),
                      initial_tokens: d,
+                     ..
                  }| {
                     (
                         (self.names[s].clone(), (si[0],)),
@@ -131,6 +136,7 @@ impl PyHsdf {
                      source: (si, sj),
                      target: (ti, tj),
                      initial_tokens: d,
+                     ..
                  }| {
                     format!("  L{si}_{} -> L{ti}_{} [label={}]\n", sj[0], tj[0], d[0]).into()
                 },
@@ -215,11 +221,15 @@ impl PySdf2D {
             .collect()
     }
 
-    fn hsdf(&self) -> PyHsdf2D {
-        PyHsdf2D {
+    fn hsdf(&self) -> PyResult<PyHsdf2D> {
+        Ok(PyHsdf2D {
             names: self.names.clone(),
-            hsdf: self.sdf.clone().into_hsdf(),
-        }
+            hsdf: self
+                .sdf
+                .clone()
+                .try_into_hsdf()
+                .map_err(|e| PyValueError::new_err(e.to_string()))?,
+        })
     }
 }
 
@@ -253,6 +263,7 @@ impl PyHsdf2D {
                      source: (s, si),
                      target: (t, ti),
                      initial_tokens,
+                     ..
                  }| {
                     (
                         (self.names[s].clone(), (si[0], si[1])),
@@ -285,6 +296,7 @@ impl PyHsdf2D {
                      source: (si, sj),
                      target: (ti, tj),
                      initial_tokens: d,
+                     ..
                  }| {
                     let sj = sj
                         .iter()
@@ -0,0 +1,275 @@
+use crate::util::strongly_connected_components;
+use crate::vector::Vector;
+use crate::{Channel, ChannelIndex, InconsistencyError, Mdsdf};
+
+use std::collections::HashMap;
+use std::fmt;
+
+use num::Rational64;
+
+/// Periodic regime reached by the self-timed execution of an [`Mdsdf`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTimedExecution {
+    /// Average time per iteration of the repetition vector once the execution is periodic.
+    /// Throughput is its inverse.
+    pub period: Rational64,
+    /// Time at which the periodic regime starts.
+    pub transient: usize,
+    /// Number of iterations in one repetition of the periodic regime.
+    pub iterations: usize,
+}
+
+/// Reason why the self-timed execution of an [`Mdsdf`] has no periodic regime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateSpaceError {
+    Inconsistent(InconsistencyError),
+    /// Some actor does not depend on every other one, so tokens can pile up without bound.
+    NotStronglyConnected,
+    /// This actor has no input channel, so it can fire infinitely often at once.
+    NoInputChannel(usize),
+    /// At `time` no actor is firing and none can start. Every actor is listed with the input
+    /// channels that hold fewer tokens than it consumes.
+    Deadlock {
+        time: usize,
+        blocked: Vec<(usize, Vec<ChannelIndex>)>,
+    },
+}
+
+impl fmt::Display for StateSpaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inconsistent(e) => e.fmt(f),
+            Self::NotStronglyConnected => write!(f, "sdf is not strongly connected"),
+            Self::NoInputChannel(actor) => write!(f, "actor {actor} has no input channel"),
+            Self::Deadlock { time, .. } => write!(f, "sdf deadlocks at time {time}"),
+        }
+    }
+}
+
+impl std::error::Error for StateSpaceError {}
+
+impl From<InconsistencyError> for StateSpaceError {
+    fn from(e: InconsistencyError) -> Self {
+        Self::Inconsistent(e)
+    }
+}
+
+/// Everything the future of a self-timed execution depends on.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct State {
+    tokens: Vec<i64>,
+    /// Remaining execution time of the ongoing firings of every actor, sorted.
+    firings: Vec<Vec<usize>>,
+    /// Index within the iteration of the next firing of every actor.
+    phases: Vec<usize>,
+}
+
+impl Mdsdf<1> {
+    /// Explores the state space of the self-timed execution, where every actor fires as soon
+    /// as it has enough tokens, with unbounded auto-concurrency, until a state recurs.
+    ///
+    /// The `k`th firing of actor `a` takes `execution_time((a, [k mod r]))` with `r` the
+    /// repetition count of `a`, like the HSDF actors of `MilpFormulation`. The graph has to be
+    /// strongly connected.
+    pub fn self_timed_execution(
+        &self,
+        mut execution_time: impl FnMut((usize, Vector<1, usize>)) -> usize,
+    ) -> Result<SelfTimedExecution, StateSpaceError> {
+        let repetition_vector = self.repetition_vector(0)?;
+
+        let mut successors = vec![Vec::new(); self.n_actors];
+        let mut inputs = vec![Vec::new(); self.n_actors];
+        let mut outputs = vec![Vec::new(); self.n_actors];
+        for (i, Channel { source, target, .. }) in self.channels.iter().enumerate() {
+            successors[*source].push(*target);
+            inputs[*target].push(i);
+            outputs[*source].push(i);
+        }
+        if strongly_connected_components(&successors).len() != 1 {
+            return Err(StateSpaceError::NotStronglyConnected);
+        }
+        if let Some(actor) = inputs.iter().position(Vec::is_empty) {
+            return Err(StateSpaceError::NoInputChannel(actor));
+        }
+
+        let execution_times = repetition_vector
+            .iter()
+            .enumerate()
+            .map(|(a, r)| {
+                (0..*r)
+                    .map(|i| execution_time((a, [i].into())))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let production_rates = self
+            .channels
+            .iter()
+            .map(|c| c.production_rate[0] as i64)
+            .collect::<Vec<_>>();
+        let consumption_rates = self
+            .channels
+            .iter()
+            .map(|c| c.consumption_rate[0] as i64)
+            .collect::<Vec<_>>();
+
+        let mut state = State {
+            tokens: self
+                .channels
+                .iter()
+                .map(|c| c.initial_tokens[0] as i64)
+                .collect(),
+            firings: vec![Vec::new(); self.n_actors],
+            phases: vec![0; self.n_actors],
+        };
+        let mut time = 0;
+        let mut completed = 0;
+        let mut visited: HashMap<State, (usize, usize)> = HashMap::new();
+        loop {
+            for (a, outputs) in outputs.iter().enumerate() {
+                let finished = state.firings[a].iter().take_while(|t| **t == 0).count();
+                state.firings[a].drain(..finished);
+                for c in outputs.iter() {
+                    state.tokens[*c] += production_rates[*c] * finished as i64;
+                }
+                if a == 0 {
+                    completed += finished;
+                }
+            }
+
+            for a in 0..self.n_actors {
+                while inputs[a]
+                    .iter()
+                    .all(|c| state.tokens[*c] >= consumption_rates[*c])
+                {
+                    for c in inputs[a].iter() {
+                        state.tokens[*c] -= consumption_rates[*c];
+                    }
+                    let phase = state.phases[a];
+                    state.firings[a].push(execution_times[a][phase]);
+                    state.phases[a] = (phase + 1) % repetition_vector[a];
+                }
+                state.firings[a].sort_unstable();
+            }
+
+            let Some(step) = state
+                .firings
+                .iter()
+                .filter_map(|f| f.first())
+                .min()
+                .copied()
+            else {
+                return Err(StateSpaceError::Deadlock {
+                    time,
+                    blocked: (0..self.n_actors)
+                        .map(|a| {
+                            let starving = inputs[a]
+                                .iter()
+                                .filter(|c| state.tokens[**c] < consumption_rates[**c])
+                                .map(|c| ChannelIndex(*c))
+                                .collect();
+                            (a, starving)
+                        })
+                        .collect(),
+                });
+            };
+
+            if let Some((start, start_completed)) = visited.get(&state) {
+                let iterations = (completed - start_completed) / repetition_vector[0];
+                return Ok(SelfTimedExecution {
+                    period: Rational64::new((time - start) as i64, iterations as i64),
+                    transient: *start,
+                    iterations,
+                });
+            }
+            visited.insert(state.clone(), (time, completed));
+
+            time += step;
+            for t in state.firings.iter_mut().flatten() {
+                *t -= step;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::Zero;
+
+    fn channel(source: usize, target: usize, rates: (usize, usize), tokens: isize) -> Channel<1> {
+        Channel {
+            production_rate: [rates.0].into(),
+            consumption_rate: [rates.1].into(),
+            source,
+            target,
+            initial_tokens: [tokens].into(),
+        }
+    }
+
+    #[test]
+    fn matches_maximum_cycle_ratio() {
+        //Example from "Exploring Trade-Offs in Buffer Requirements and Throughput Constraints for Synchronous Dataflow Graphs"
+        let mut sdf = Mdsdf::<1>::new(3);
+        sdf.add_channel(channel(0, 1, (2, 3), 0));
+        sdf.add_channel(channel(1, 2, (1, 2), 0));
+        sdf.add_channel(channel(0, 0, (1, 1), 1));
+        sdf.add_channel(channel(1, 1, (1, 1), 1));
+        sdf.add_channel(channel(2, 2, (1, 1), 1));
+        sdf.add_channel(channel(1, 0, (3, 2), 4));
+        sdf.add_channel(channel(2, 1, (2, 1), 2));
+        let execution_time = |(a, _): (usize, Vector<1, usize>)| [1, 2, 2][a];
+
+        let execution = sdf.self_timed_execution(execution_time).unwrap();
+        let critical_cycle = sdf.hsdf().maximum_cycle_ratio(execution_time).unwrap();
+        assert_eq!(execution.period, Rational64::from_integer(7));
+        assert_eq!(execution.period, critical_cycle[0].period);
+    }
+
+    #[test]
+    fn zero_execution_time() {
+        let mut sdf = Mdsdf::<1>::new(2);
+        sdf.add_channel(channel(0, 1, (1, 1), 0));
+        sdf.add_channel(channel(1, 0, (1, 1), 1));
+
+        let execution = sdf.self_timed_execution(|_| 0).unwrap();
+        assert_eq!(execution.period, Rational64::zero());
+    }
+
+    #[test]
+    fn deadlock() {
+        let mut sdf = Mdsdf::<1>::new(2);
+        let c0 = sdf.add_channel(channel(0, 1, (2, 1), 0));
+        let c1 = sdf.add_channel(channel(1, 0, (1, 2), 1));
+
+        assert_eq!(
+            sdf.self_timed_execution(|_| 1),
+            Err(StateSpaceError::Deadlock {
+                time: 0,
+                blocked: vec![(0, vec![c1]), (1, vec![c0])],
+            })
+        );
+    }
+
+    #[test]
+    fn not_strongly_connected() {
+        let mut sdf = Mdsdf::<1>::new(2);
+        sdf.add_channel(channel(0, 1, (1, 1), 0));
+        sdf.add_channel(channel(0, 0, (1, 1), 1));
+        sdf.add_channel(channel(1, 1, (1, 1), 1));
+
+        assert_eq!(
+            sdf.self_timed_execution(|_| 1),
+            Err(StateSpaceError::NotStronglyConnected)
+        );
+    }
+
+    #[test]
+    fn no_input_channel() {
+        let sdf = Mdsdf::<1>::new(1);
+
+        assert_eq!(
+            sdf.self_timed_execution(|_| 1),
+            Err(StateSpaceError::NoInputChannel(0))
+        );
+    }
+}
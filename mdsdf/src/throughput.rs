@@ -0,0 +1,392 @@
+use crate::util::strongly_connected_components;
+use crate::vector::Vector;
+use crate::{Hsdf, HsdfChannel};
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+use num::{Rational64, Zero};
+
+/// Throughput bottleneck of one dimension of an [`Hsdf`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CriticalCycle<const N: usize> {
+    /// Largest ratio over all cycles of their execution time over their tokens in this
+    /// dimension. The throughput of `MilpFormulation` is its inverse.
+    pub period: Rational64,
+    /// HSDF actors of a cycle attaining `period`, in channel order. Empty when there is no
+    /// cycle, in which case `period` is zero.
+    pub cycle: Vec<(usize, Vector<N, usize>)>,
+}
+
+/// Reason why an [`Hsdf`] has no maximum cycle ratio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThroughputError<const N: usize> {
+    /// The HSDF actors of `cycle` wait on each other without tokens in `dimension`.
+    Deadlock {
+        dimension: usize,
+        cycle: Vec<(usize, Vector<N, usize>)>,
+    },
+    /// The channel from `source` to `target` holds a negative number of tokens in `dimension`.
+    NegativeTokens {
+        dimension: usize,
+        source: (usize, Vector<N, usize>),
+        target: (usize, Vector<N, usize>),
+    },
+}
+
+impl<const N: usize> fmt::Display for ThroughputError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deadlock { dimension, cycle } => write!(
+                f,
+                "actors {cycle:?} form a cycle without tokens in dimension {dimension}"
+            ),
+            Self::NegativeTokens {
+                dimension,
+                source,
+                target,
+            } => write!(
+                f,
+                "channel from {source:?} to {target:?} has negative tokens in dimension {dimension}"
+            ),
+        }
+    }
+}
+
+impl<const N: usize> std::error::Error for ThroughputError<N> {}
+
+/// Channel of the graph handed to [`howard`]: source, target, weight and transit time.
+type Edge = (usize, usize, i64, i64);
+
+impl<const N: usize> Hsdf<'_, N> {
+    /// Computes for every dimension the maximum cycle ratio of the graph, the execution time of
+    /// the actors on a cycle over the tokens on its channels, with Howard's policy iteration in
+    /// exact arithmetic.
+    pub fn maximum_cycle_ratio(
+        &self,
+        mut execution_time: impl FnMut((usize, Vector<N, usize>)) -> usize,
+    ) -> Result<Vec<CriticalCycle<N>>, ThroughputError<N>> {
+        let actors = self.actors().collect::<Vec<_>>();
+        let indices: BTreeMap<(usize, Vector<N, usize>), usize> =
+            actors.iter().enumerate().map(|(i, a)| (*a, i)).collect();
+        let execution_times = actors
+            .iter()
+            .map(|a| execution_time(*a) as i64)
+            .collect::<Vec<_>>();
+        let channels = self
+            .channels()
+            .map(
+                |HsdfChannel {
+                     source,
+                     target,
+                     initial_tokens,
+                     ..
+                 }| (indices[&source], indices[&target], initial_tokens),
+            )
+            .collect::<Vec<_>>();
+
+        let mut successors = vec![Vec::new(); actors.len()];
+        for (source, target, _) in channels.iter() {
+            successors[*source].push(*target);
+        }
+        let components = strongly_connected_components(&successors);
+        let mut component_of = vec![0; actors.len()];
+        let mut local_index = vec![0; actors.len()];
+        for (k, component) in components.iter().enumerate() {
+            for (i, a) in component.iter().enumerate() {
+                component_of[*a] = k;
+                local_index[*a] = i;
+            }
+        }
+
+        (0..N)
+            .map(|d| {
+                if let Some((source, target, _)) = channels.iter().find(|c| c.2[d] < 0) {
+                    return Err(ThroughputError::NegativeTokens {
+                        dimension: d,
+                        source: actors[*source],
+                        target: actors[*target],
+                    });
+                }
+
+                let mut result = CriticalCycle {
+                    period: Rational64::zero(),
+                    cycle: Vec::new(),
+                };
+                for (k, component) in components.iter().enumerate() {
+                    let edges = channels
+                        .iter()
+                        .filter(|(s, t, _)| component_of[*s] == k && component_of[*t] == k)
+                        .map(|(s, t, tokens)| {
+                            (
+                                local_index[*s],
+                                local_index[*t],
+                                execution_times[*s],
+                                tokens[d] as i64,
+                            )
+                        })
+                        .collect::<Vec<Edge>>();
+                    if edges.is_empty() {
+                        continue;
+                    }
+                    if let Some(cycle) = zero_transit_cycle(component.len(), &edges) {
+                        return Err(ThroughputError::Deadlock {
+                            dimension: d,
+                            cycle: cycle.into_iter().map(|i| actors[component[i]]).collect(),
+                        });
+                    }
+                    let (period, cycle) = howard(component.len(), &edges);
+                    if result.cycle.is_empty() || period > result.period {
+                        result = CriticalCycle {
+                            period,
+                            cycle: cycle.into_iter().map(|i| actors[component[i]]).collect(),
+                        };
+                    }
+                }
+                Ok(result)
+            })
+            .collect()
+    }
+}
+
+/// Finds a cycle of edges without transit time, listing its nodes in edge order.
+fn zero_transit_cycle(n: usize, edges: &[Edge]) -> Option<Vec<usize>> {
+    let mut successors = vec![Vec::new(); n];
+    for (source, target, _, transit) in edges.iter() {
+        if *transit == 0 {
+            successors[*source].push(*target);
+        }
+    }
+    let component = strongly_connected_components(&successors)
+        .into_iter()
+        .find(|c| c.len() > 1 || successors[c[0]].contains(&c[0]))?;
+
+    // Walk back from the root along predecessors found by a breadth first search inside the
+    // component until the root is reached again.
+    let root = component[0];
+    let mut predecessor = vec![None; n];
+    let mut queue = VecDeque::from([root]);
+    while let Some(v) = queue.pop_front() {
+        for w in successors[v].iter() {
+            if component.binary_search(w).is_ok() && predecessor[*w].is_none() {
+                predecessor[*w] = Some(v);
+                if *w == root {
+                    queue.clear();
+                    break;
+                }
+                queue.push_back(*w);
+            }
+        }
+    }
+    let mut cycle = vec![root];
+    let mut v = predecessor[root].unwrap();
+    while v != root {
+        cycle.push(v);
+        v = predecessor[v].unwrap();
+    }
+    cycle[1..].reverse();
+    Some(cycle)
+}
+
+/// Howard's policy iteration for the maximum cycle ratio of a strongly connected graph whose
+/// cycles all have positive transit time. Returns the ratio and the nodes of a critical cycle.
+fn howard(n: usize, edges: &[Edge]) -> (Rational64, Vec<usize>) {
+    let mut out = vec![Vec::new(); n];
+    for (i, (source, _, _, _)) in edges.iter().enumerate() {
+        out[*source].push(i);
+    }
+    let mut policy = out.iter().map(|e| e[0]).collect::<Vec<_>>();
+
+    loop {
+        let (chi, x) = evaluate_policy(edges, &policy);
+
+        let mut improved = false;
+        for u in 0..n {
+            let best = *out[u].iter().max_by_key(|e| chi[edges[**e].1]).unwrap();
+            if chi[edges[best].1] > chi[u] {
+                policy[u] = best;
+                improved = true;
+            }
+        }
+        if improved {
+            continue;
+        }
+
+        for u in 0..n {
+            let value = |e: usize| {
+                let (_, target, weight, transit) = edges[e];
+                x[target] + weight - chi[u] * transit
+            };
+            let best = *out[u]
+                .iter()
+                .filter(|e| chi[edges[**e].1] == chi[u])
+                .max_by_key(|e| value(**e))
+                .unwrap();
+            if value(best) > x[u] {
+                policy[u] = best;
+                improved = true;
+            }
+        }
+        if improved {
+            continue;
+        }
+
+        let start = (0..n).max_by_key(|u| chi[*u]).unwrap();
+        let mut seen = vec![false; n];
+        let mut u = start;
+        while !seen[u] {
+            seen[u] = true;
+            u = edges[policy[u]].1;
+        }
+        let mut cycle = vec![u];
+        let mut v = edges[policy[u]].1;
+        while v != u {
+            cycle.push(v);
+            v = edges[policy[v]].1;
+        }
+        let first = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap();
+        cycle.rotate_left(first);
+        return (chi[start], cycle);
+    }
+}
+
+/// Computes the cycle ratio `chi` every node reaches under `policy`, and a potential `x` such
+/// that `x[u] = weight - chi[u] * transit + x[target]` along every policy edge.
+fn evaluate_policy(edges: &[Edge], policy: &[usize]) -> (Vec<Rational64>, Vec<Rational64>) {
+    let n = policy.len();
+    let mut chi = vec![Rational64::zero(); n];
+    let mut x = vec![Rational64::zero(); n];
+    let mut done = vec![false; n];
+    let mut on_path = vec![usize::MAX; n];
+
+    for start in 0..n {
+        if done[start] {
+            continue;
+        }
+        let mut path = Vec::new();
+        let mut u = start;
+        while !done[u] && on_path[u] == usize::MAX {
+            on_path[u] = path.len();
+            path.push(u);
+            u = edges[policy[u]].1;
+        }
+
+        let mut tail = path.len();
+        if !done[u] {
+            // The path closed a new cycle starting at `u`.
+            let cycle = &path[on_path[u]..];
+            let (weight, transit) = cycle.iter().fold((0, 0), |(w, t), v| {
+                let (_, _, weight, transit) = edges[policy[*v]];
+                (w + weight, t + transit)
+            });
+            let ratio = Rational64::new(weight, transit);
+            x[u] = Rational64::zero();
+            chi[u] = ratio;
+            done[u] = true;
+            for v in cycle[1..].iter().rev() {
+                let (_, target, weight, transit) = edges[policy[*v]];
+                chi[*v] = ratio;
+                x[*v] = x[target] + weight - ratio * transit;
+                done[*v] = true;
+            }
+            tail = on_path[u];
+        }
+        for v in path[..tail].iter().rev() {
+            let (_, target, weight, transit) = edges[policy[*v]];
+            chi[*v] = chi[target];
+            x[*v] = x[target] + weight - chi[*v] * transit;
+            done[*v] = true;
+        }
+        for v in path {
+            on_path[v] = usize::MAX;
+        }
+    }
+
+    (chi, x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Channel, Mdsdf};
+
+    fn channel(source: usize, target: usize, rates: (usize, usize), tokens: isize) -> Channel<1> {
+        Channel {
+            production_rate: [rates.0].into(),
+            consumption_rate: [rates.1].into(),
+            source,
+            target,
+            initial_tokens: [tokens].into(),
+        }
+    }
+
+    #[test]
+    fn homogeneous() {
+        let mut sdf = Mdsdf::<1>::new(2);
+        sdf.add_channel(channel(0, 1, (1, 1), 0));
+        sdf.add_channel(channel(1, 0, (1, 1), 2));
+        let hsdf = sdf.hsdf();
+        let execution_time = |(a, _): (usize, Vector<1, usize>)| [3, 2][a];
+
+        assert_eq!(
+            hsdf.maximum_cycle_ratio(execution_time),
+            Ok(vec![CriticalCycle {
+                period: Rational64::new(5, 2),
+                cycle: vec![(0, [0].into()), (1, [0].into())],
+            }])
+        );
+
+        sdf.add_channel(channel(0, 0, (1, 1), 1));
+        let hsdf = sdf.hsdf();
+        assert_eq!(
+            hsdf.maximum_cycle_ratio(execution_time),
+            Ok(vec![CriticalCycle {
+                period: Rational64::from_integer(3),
+                cycle: vec![(0, [0].into())],
+            }])
+        );
+    }
+
+    #[test]
+    fn multirate() {
+        //Example from "Exploring Trade-Offs in Buffer Requirements and Throughput Constraints for Synchronous Dataflow Graphs"
+        let mut sdf = Mdsdf::<1>::new(3);
+        sdf.add_channel(channel(0, 1, (2, 3), 0));
+        sdf.add_channel(channel(1, 2, (1, 2), 0));
+        sdf.add_channel(channel(0, 0, (1, 1), 1));
+        sdf.add_channel(channel(1, 1, (1, 1), 1));
+        sdf.add_channel(channel(2, 2, (1, 1), 1));
+        sdf.add_channel(channel(1, 0, (3, 2), 4));
+        sdf.add_channel(channel(2, 1, (2, 1), 2));
+        let hsdf = sdf.hsdf();
+
+        assert_eq!(
+            hsdf.maximum_cycle_ratio(|(a, _)| [1, 2, 2][a]),
+            Ok(vec![CriticalCycle {
+                period: Rational64::from_integer(7),
+                cycle: vec![
+                    (0, [2].into()),
+                    (1, [1].into()),
+                    (2, [0].into()),
+                    (1, [0].into())
+                ],
+            }])
+        );
+    }
+
+    #[test]
+    fn deadlock() {
+        let mut sdf = Mdsdf::<1>::new(2);
+        sdf.add_channel(channel(0, 1, (2, 1), 0));
+        sdf.add_channel(channel(1, 0, (1, 2), 1));
+        let hsdf = sdf.hsdf();
+
+        assert_eq!(
+            hsdf.maximum_cycle_ratio(|_| 1),
+            Err(ThroughputError::Deadlock {
+                dimension: 0,
+                cycle: vec![(0, [0].into()), (1, [0].into())],
+            })
+        );
+    }
+}
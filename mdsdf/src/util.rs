@@ -1,84 +1,268 @@
 use crate::vector::Vector;
 
 use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
 
-use ndarray::prelude::*;
-use num::integer::lcm;
-use num::Rational32;
-use num::Zero;
+use num::integer::Integer;
+use num::{CheckedMul, One, Rational64};
 
-fn rref<I>(mut out: Array2<Rational32>) -> Array2<Rational32> {
-    let mut pivot = 0;
-    let (rows, cols) = out.raw_dim().into_pattern();
+/// Reason why [`repetition_vector`] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepetitionVectorError {
+    /// The channel with this index produces or consumes no tokens.
+    ZeroRate(usize),
+    /// Indices of the channels of a cycle whose rates disagree, in traversal order.
+    Inconsistent(Vec<usize>),
+    /// A firing ratio or an entry of the repetition vector does not fit in 64 bits.
+    Overflow,
+}
+
+/// Computes the repetition vector of the graph formed by `channels`, given as `(source,
+/// target, production_rate, consumption_rate)`, in time linear in its size. Every weakly
+/// connected component is normalised on its own to the smallest positive integer solution.
+pub fn repetition_vector(
+    n_actors: usize,
+    channels: &[(usize, usize, usize, usize)],
+) -> Result<Vec<usize>, RepetitionVectorError> {
+    let ratios = firing_ratios(n_actors, channels)?;
 
-    'outer: for r in 0..rows {
-        if cols <= pivot {
-            break;
+    let mut result = vec![0; n_actors];
+    for component in connected_components(n_actors, channels.iter().map(|(s, t, _, _)| (*s, *t))) {
+        let mut multiplier = 1i64;
+        for a in component.iter() {
+            let denom = *ratios[*a].denom();
+            multiplier = (multiplier / multiplier.gcd(&denom))
+                .checked_mul(denom)
+                .ok_or(RepetitionVectorError::Overflow)?;
+        }
+        let repetitions = component
+            .iter()
+            .map(|a| {
+                ratios[*a]
+                    .numer()
+                    .checked_mul(&(multiplier / ratios[*a].denom()))
+                    .ok_or(RepetitionVectorError::Overflow)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let divisor = repetitions.iter().fold(0, |a, b| a.gcd(b));
+        for (a, r) in component.iter().zip(repetitions) {
+            result[*a] =
+                usize::try_from(r / divisor).map_err(|_| RepetitionVectorError::Overflow)?;
         }
-        let mut i = r;
-        while out[[i, pivot]].is_zero() {
-            i += 1;
-            if i == rows {
-                i = r;
-                pivot += 1;
-                if cols == pivot {
-                    break 'outer;
+    }
+
+    Ok(result)
+}
+
+/// Propagates firing ratios along a breadth first spanning forest of the undirected graph
+/// formed by `channels`, given as `(source, target, production_rate, consumption_rate)`, and
+/// checks the remaining channels against them. Every tree is rooted at its lowest actor, which
+/// gets ratio one.
+pub fn firing_ratios(
+    n_actors: usize,
+    channels: &[(usize, usize, usize, usize)],
+) -> Result<Vec<Rational64>, RepetitionVectorError> {
+    let rates = channels
+        .iter()
+        .enumerate()
+        .map(|(i, (_, _, production_rate, consumption_rate))| {
+            if *production_rate == 0 || *consumption_rate == 0 {
+                return Err(RepetitionVectorError::ZeroRate(i));
+            }
+            let production_rate =
+                i64::try_from(*production_rate).map_err(|_| RepetitionVectorError::Overflow)?;
+            let consumption_rate =
+                i64::try_from(*consumption_rate).map_err(|_| RepetitionVectorError::Overflow)?;
+            Ok(Rational64::new(production_rate, consumption_rate))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let incident = incident_channels(n_actors, channels.iter().map(|(s, t, _, _)| (*s, *t)));
+
+    let mut ratios: Vec<Option<Rational64>> = vec![None; n_actors];
+    let mut parent: Vec<Option<usize>> = vec![None; n_actors];
+    for root in 0..n_actors {
+        if ratios[root].is_some() {
+            continue;
+        }
+        ratios[root] = Some(Rational64::one());
+        let mut queue = VecDeque::from([root]);
+        while let Some(actor) = queue.pop_front() {
+            let ratio = ratios[actor].unwrap();
+            for c in incident[actor].iter() {
+                let (source, target, _, _) = channels[*c];
+                let other = if source == actor { target } else { source };
+                if ratios[other].is_none() {
+                    let rate = if other == target {
+                        rates[*c]
+                    } else {
+                        rates[*c].recip()
+                    };
+                    ratios[other] = Some(
+                        ratio
+                            .checked_mul(&rate)
+                            .ok_or(RepetitionVectorError::Overflow)?,
+                    );
+                    parent[other] = Some(*c);
+                    queue.push_back(other);
+                } else {
+                    let produced = ratios[source]
+                        .unwrap()
+                        .checked_mul(&rates[*c])
+                        .ok_or(RepetitionVectorError::Overflow)?;
+                    if produced != ratios[target].unwrap() {
+                        return Err(RepetitionVectorError::Inconsistent(tree_cycle(
+                            channels, &parent, *c,
+                        )));
+                    }
                 }
             }
         }
+    }
 
-        if r != i {
-            let (mut row1, mut row2) = out.multi_slice_mut((s![r, ..], s![i, ..]));
-            row1.iter_mut()
-                .zip(row2.iter_mut())
-                .for_each(|(a, b)| std::mem::swap(a, b));
-        }
+    Ok(ratios.into_iter().map(Option::unwrap).collect())
+}
 
-        let divisor = out[[r, pivot]];
-        if !divisor.is_zero() {
-            out.row_mut(r).iter_mut().for_each(|e| *e /= divisor);
+/// Splits the actors into weakly connected components, each sorted by actor index. Components
+/// are ordered by their lowest actor.
+pub fn connected_components(
+    n_actors: usize,
+    channels: impl Iterator<Item = (usize, usize)> + Clone,
+) -> Vec<Vec<usize>> {
+    let incident = incident_channels(n_actors, channels.clone());
+    let channels = channels.collect::<Vec<_>>();
+
+    let mut visited = vec![false; n_actors];
+    let mut result = Vec::new();
+    for root in 0..n_actors {
+        if visited[root] {
+            continue;
         }
-        for j in 0..rows {
-            if j != r {
-                let hold = out[[j, pivot]];
-                //out.slice_mut(s![r,..]).iter_mut().for_each(|e| *e -= hold * *e);
-                for k in 0..cols {
-                    let t = out[[r, k]];
-                    out[[j, k]] -= hold * t;
+        visited[root] = true;
+        let mut component = vec![root];
+        let mut stack = vec![root];
+        while let Some(actor) = stack.pop() {
+            for c in incident[actor].iter() {
+                let (source, target) = channels[*c];
+                let other = if source == actor { target } else { source };
+                if !visited[other] {
+                    visited[other] = true;
+                    component.push(other);
+                    stack.push(other);
                 }
             }
         }
-        pivot += 1;
+        component.sort_unstable();
+        result.push(component);
     }
-
-    out
+    result
 }
 
-pub fn repetition_vector(topology_matrix: &Array2<i32>) -> Option<Array1<usize>> {
-    let rref = rref::<Rational32>(Array2::<Rational32>::from_shape_fn(
-        topology_matrix.raw_dim(),
-        |i| Rational32::from(topology_matrix[i]),
-    ));
-    let (rows, cols) = rref.raw_dim().into_pattern();
+/// Strongly connected components of the directed graph with adjacency lists `successors`, each
+/// sorted, in reverse topological order.
+pub fn strongly_connected_components(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = successors.len();
+    let mut index = vec![usize::MAX; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut calls: Vec<(usize, usize)> = Vec::new();
+    let mut next = 0;
+    let mut result = Vec::new();
+
+    for root in 0..n {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        index[root] = next;
+        low[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        calls.push((root, 0));
+        while let Some(&(v, i)) = calls.last() {
+            if let Some(&w) = successors[v].get(i) {
+                calls.last_mut().unwrap().1 += 1;
+                if index[w] == usize::MAX {
+                    index[w] = next;
+                    low[w] = next;
+                    next += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(u, _)) = calls.last() {
+                low[u] = low[u].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut component = Vec::new();
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                result.push(component);
+            }
+        }
+    }
+    result
+}
 
-    if rref.slice(s![(cols - 1).., ..]) != Array2::<Rational32>::zeros((rows + 1 - cols, cols))
-        || rref.slice(s![..(cols - 1), ..(cols - 1)]) != Array2::<Rational32>::eye(cols - 1)
-    {
-        return None; // rank is not correct
+/// Lists for every actor the channels that start or end at it, self loops only once.
+fn incident_channels(
+    n_actors: usize,
+    channels: impl Iterator<Item = (usize, usize)>,
+) -> Vec<Vec<usize>> {
+    let mut incident = vec![Vec::new(); n_actors];
+    for (i, (source, target)) in channels.enumerate() {
+        incident[source].push(i);
+        if source != target {
+            incident[target].push(i);
+        }
     }
-    let unnormalized = rref.slice(s![..(cols - 1), cols - 1]);
+    incident
+}
 
-    let multiplier = unnormalized
+/// Closes the cycle formed by the non-tree channel `channel` and the spanning tree `parent`.
+fn tree_cycle(
+    channels: &[(usize, usize, usize, usize)],
+    parent: &[Option<usize>],
+    channel: usize,
+) -> Vec<usize> {
+    let ancestors = |mut actor: usize| {
+        let mut result = vec![(actor, parent[actor])];
+        while let Some(c) = parent[actor] {
+            let (source, target, _, _) = channels[c];
+            actor = if source == actor { target } else { source };
+            result.push((actor, parent[actor]));
+        }
+        result
+    };
+    let (source, target, _, _) = channels[channel];
+    let from_source = ancestors(source);
+    let from_target = ancestors(target);
+    let (i, j) = from_source
         .iter()
-        .map(Rational32::denom)
-        .fold(1, |cd, e| lcm(cd, *e));
+        .enumerate()
+        .find_map(|(i, (a, _))| from_target.iter().position(|(b, _)| a == b).map(|j| (i, j)))
+        .expect("actors of a spanning tree share the root");
 
-    Some(Array1::<usize>::from_iter(
-        unnormalized
-            .iter()
-            .map(|e| *(-e * multiplier).numer() as usize)
-            .chain(std::iter::once(multiplier as usize)),
-    ))
+    from_source[..i]
+        .iter()
+        .rev()
+        .map(|(_, c)| c.unwrap())
+        .chain(std::iter::once(channel))
+        .chain(from_target[..j].iter().map(|(_, c)| c.unwrap()))
+        .collect()
 }
 
 pub struct BoundedIterator<'a, const N: usize> {
@@ -127,17 +311,94 @@ mod tests {
     use super::*;
     #[test]
     fn test1() {
-        let topology_matrix = arr2(&[[2, -1, 0], [0, 1, -1], [2, 0, -1], [2, 0, -1]]);
-        let repetition_vector = repetition_vector(&topology_matrix);
+        let channels = [(0, 1, 2, 1), (1, 2, 1, 1), (0, 2, 2, 1), (0, 2, 2, 1)];
+        let repetition_vector = repetition_vector(3, &channels);
 
-        assert_eq!(repetition_vector, Some(arr1(&[1, 2, 2])));
+        assert_eq!(repetition_vector, Ok(vec![1, 2, 2]));
     }
 
     #[test]
     fn test2() {
-        let topology_matrix = arr2(&[[2, -3, 0], [1, 0, -1]]);
-        let repetition_vector = repetition_vector(&topology_matrix);
+        let channels = [(0, 1, 2, 3), (0, 2, 1, 1)];
+        let repetition_vector = repetition_vector(3, &channels);
+
+        assert_eq!(repetition_vector, Ok(vec![3, 2, 3]));
+    }
+
+    #[test]
+    fn no_channels() {
+        assert_eq!(repetition_vector(0, &[]), Ok(vec![]));
+        assert_eq!(repetition_vector(3, &[]), Ok(vec![1, 1, 1]));
+    }
+
+    #[test]
+    fn zero_rate() {
+        let channels = [(0, 1, 1, 1), (0, 1, 2, 0)];
+        assert_eq!(
+            repetition_vector(2, &channels),
+            Err(RepetitionVectorError::ZeroRate(1))
+        );
+    }
+
+    #[test]
+    fn h263() {
+        let channels = [(0, 1, 594, 1), (1, 2, 1, 1), (2, 3, 1, 594)];
+        assert_eq!(repetition_vector(4, &channels), Ok(vec![1, 594, 594, 1]));
+    }
 
-        assert_eq!(repetition_vector, Some(arr1(&[3, 2, 3])));
+    #[test]
+    fn overflow() {
+        let channels = (0..64).map(|i| (i, i + 1, 1, 2)).collect::<Vec<_>>();
+        assert_eq!(
+            repetition_vector(65, &channels),
+            Err(RepetitionVectorError::Overflow)
+        );
+    }
+
+    #[test]
+    fn inconsistent_cycle() {
+        let channels = [(0, 1, 2, 1), (1, 2, 1, 1), (2, 3, 1, 1), (0, 3, 1, 1)];
+        assert_eq!(
+            firing_ratios(4, &channels),
+            Err(RepetitionVectorError::Inconsistent(vec![0, 1, 2, 3]))
+        );
+
+        let channels = [(0, 1, 1, 1), (1, 1, 2, 1)];
+        assert_eq!(
+            firing_ratios(2, &channels),
+            Err(RepetitionVectorError::Inconsistent(vec![1]))
+        );
+    }
+
+    #[test]
+    fn consistent_ratios() {
+        let channels = [(0, 1, 2, 3), (0, 2, 1, 1)];
+        assert_eq!(
+            firing_ratios(4, &channels),
+            Ok(vec![
+                Rational64::one(),
+                Rational64::new(2, 3),
+                Rational64::one(),
+                Rational64::one(),
+            ])
+        );
+    }
+
+    #[test]
+    fn strongly_connected() {
+        let successors = [vec![1], vec![2, 3], vec![0], vec![3, 4], vec![]];
+        assert_eq!(
+            strongly_connected_components(&successors),
+            vec![vec![4], vec![3], vec![0, 1, 2]]
+        );
+    }
+
+    #[test]
+    fn components() {
+        let channels = [(3, 1), (4, 4), (0, 2)];
+        assert_eq!(
+            connected_components(5, channels.iter().copied()),
+            vec![vec![0, 2], vec![1, 3], vec![4]]
+        );
     }
 }
@@ -0,0 +1,153 @@
+use crate::{solver::Solution, Dependency, ExecutionTimeT, MilpFormulation, NameT, Origin};
+use mdsdf::{util::strongly_connected_components, ChannelIndex};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Slack below which a constraint counts as tight, relative to its right hand side.
+const TOLERANCE: f64 = 1e-6;
+
+/// Cycle of dependencies that limits the throughput of a solved [`MilpFormulation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bottleneck<const N: usize> {
+    pub dimension: usize,
+    /// Dependencies in traversal order: the target of each is the source of the next one.
+    pub cycle: Vec<Dependency<N>>,
+}
+
+impl<const N: usize> Bottleneck<N> {
+    /// Channels of the [`mdsdf::Mdsdf`] on the cycle, including those whose buffer is on it.
+    pub fn channels(&self) -> Vec<ChannelIndex> {
+        let channels = self
+            .cycle
+            .iter()
+            .map(|d| d.channel)
+            .collect::<BTreeSet<_>>();
+        channels.into_iter().collect()
+    }
+
+    /// Actors of the [`mdsdf::Mdsdf`] on the cycle.
+    pub fn actors(&self) -> Vec<usize> {
+        let actors = self
+            .cycle
+            .iter()
+            .map(|d| d.source.0)
+            .collect::<BTreeSet<_>>();
+        actors.into_iter().collect()
+    }
+}
+
+impl<'a, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>
+    MilpFormulation<'a, N, ExecutionTime, Name>
+{
+    /// Finds a cycle of dependencies in `dimension` whose constraints are tight in `solution`.
+    /// Adding up the constraints along it shows that the throughput cannot be higher.
+    ///
+    /// Returns `None` if the solution has no values or no such cycle exists, which happens when
+    /// other constraints, like those of a cyclic schedule, limit the throughput.
+    pub fn bottleneck(&self, solution: &Solution, dimension: usize) -> Option<Bottleneck<N>> {
+        let indices: BTreeMap<(usize, mdsdf::vector::Vector<N, usize>), usize> =
+            self.u.keys().enumerate().map(|(i, a)| (*a, i)).collect();
+
+        let mut edges = vec![Vec::new(); indices.len()];
+        for (constr, origin) in self.origins.iter() {
+            let Origin::Dependency(dependency) = origin else {
+                continue;
+            };
+            if dependency.dimension != dimension {
+                continue;
+            }
+            let Ok(data) = self.model.constr(*constr) else {
+                continue;
+            };
+            let slack = solution.slack(&data.constraint)?;
+            if slack <= TOLERANCE * (1.0 + data.constraint.rhs.abs()) {
+                edges[indices[&dependency.source]].push((indices[&dependency.target], *dependency));
+            }
+        }
+
+        let successors = edges
+            .iter()
+            .map(|e| e.iter().map(|(t, _)| *t).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let component = strongly_connected_components(&successors)
+            .into_iter()
+            .find(|c| c.len() > 1 || successors[c[0]].contains(&c[0]))?;
+        let component = component.into_iter().collect::<BTreeSet<_>>();
+
+        // Every node of the component has a tight edge within it, so following them from any
+        // node eventually closes a cycle.
+        let mut position = BTreeMap::new();
+        let mut path: Vec<Dependency<N>> = Vec::new();
+        let mut node = *component.first()?;
+        while !position.contains_key(&node) {
+            position.insert(node, path.len());
+            let (next, dependency) = edges[node].iter().find(|(t, _)| component.contains(t))?;
+            path.push(*dependency);
+            node = *next;
+        }
+        Some(Bottleneck {
+            dimension,
+            cycle: path.split_off(position[&node]),
+        })
+    }
+}
+
+#[cfg(all(test, any(feature = "microlp", feature = "grb")))]
+mod tests {
+    use crate::{model::ModelSense, solver::DefaultSolver, MilpFormulation};
+    use mdsdf::{vector::Vector, Channel, Mdsdf};
+    use std::borrow::Cow;
+
+    #[test]
+    fn critical_cycle() {
+        //Example from "Exploring Trade-Offs in Buffer Requirements and Throughput Constraints for Synchronous Dataflow Graphs"
+        let mut sdf = Mdsdf::<1>::new(3);
+        for (source, target, production_rate, consumption_rate, initial_tokens) in [
+            (0, 1, 2, 3, 0),
+            (1, 2, 1, 2, 0),
+            (0, 0, 1, 1, 1),
+            (1, 1, 1, 1, 1),
+            (2, 2, 1, 1, 1),
+            (1, 0, 3, 2, 4),
+            (2, 1, 2, 1, 2),
+        ] {
+            sdf.add_channel(Channel {
+                production_rate: [production_rate].into(),
+                consumption_rate: [consumption_rate].into(),
+                source,
+                target,
+                initial_tokens: [initial_tokens].into(),
+            });
+        }
+        let hsdf = sdf.hsdf();
+        let execution_time = |(a, _): (usize, Vector<1, usize>)| [1, 2, 2][a];
+        let mut milp = MilpFormulation::new(Cow::Borrowed(&hsdf), execution_time, |(a, i)| {
+            format!("{}({})", ["a", "b", "c"][a], i[0])
+        })
+        .unwrap();
+        milp.model
+            .set_objective(milp.throughputs[0], ModelSense::Maximize)
+            .unwrap();
+        let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+        let bottleneck = milp.bottleneck(&solution, 0).unwrap();
+
+        let cycle = &bottleneck.cycle;
+        for (d, next) in cycle.iter().zip(cycle.iter().cycle().skip(1)) {
+            assert_eq!(d.target, next.source);
+        }
+        let time = cycle
+            .iter()
+            .map(|d| execution_time(d.source))
+            .sum::<usize>();
+        let tokens = cycle
+            .iter()
+            .map(|d| {
+                hsdf.channels()
+                    .find(|c| (c.channel, c.source, c.target) == (d.channel, d.source, d.target))
+                    .unwrap()
+                    .initial_tokens[0]
+            })
+            .sum::<isize>();
+        assert_eq!(time, 7 * tokens as usize);
+        assert_eq!(bottleneck.actors(), vec![0, 1, 2]);
+    }
+}
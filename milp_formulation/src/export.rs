@@ -0,0 +1,341 @@
+use crate::model::{ConstrData, Model, ModelSense, Sense, VarData, VarType};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Name of the objective row in both formats.
+const OBJECTIVE: &str = "obj";
+
+/// Number of terms written per line of an LP expression.
+const TERMS_PER_LINE: usize = 8;
+
+/// Makes `names` valid and unique in the LP and MPS formats. Characters outside of what the LP
+/// format allows become `_`, empty names become `{prefix}{index}` and duplicates get a `_{k}`
+/// suffix, so names that were already valid and unique are kept as is.
+fn sanitize<'a>(
+    names: impl Iterator<Item = &'a str>,
+    prefix: &str,
+    reserved: &[&str],
+) -> Vec<String> {
+    let mut used: HashSet<String> = reserved.iter().map(|n| n.to_string()).collect();
+    names
+        .enumerate()
+        .map(|(i, name)| {
+            let mut name = name
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || "!\"#$%&()/,.;?@_`'{}|~".contains(c) {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>();
+            if name.is_empty() {
+                name = format!("{prefix}{i}");
+            } else if name.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+                name.insert(0, '_');
+            }
+            let mut unique = name.clone();
+            let mut k = 1;
+            while !used.insert(unique.clone()) {
+                unique = format!("{name}_{k}");
+                k += 1;
+            }
+            unique
+        })
+        .collect()
+}
+
+fn number(x: f64) -> String {
+    if x == f64::INFINITY {
+        "+inf".to_string()
+    } else if x == f64::NEG_INFINITY {
+        "-inf".to_string()
+    } else {
+        // Adding zero turns -0 into 0.
+        (x + 0.0).to_string()
+    }
+}
+
+impl Model {
+    /// Writes the model to `path`, in the LP format if it ends in `.lp` and in the free MPS
+    /// format if it ends in `.mps`.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut file = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("lp") => self.write_lp(&mut file)?,
+            Some("mps") => self.write_mps(&mut file)?,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown model format {}", path.display()),
+                ))
+            }
+        }
+        file.flush()
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        sanitize(self.vars().map(|(_, v)| v.name.as_str()), "x", &[])
+    }
+
+    fn constr_names(&self) -> Vec<String> {
+        sanitize(
+            self.constrs().map(|(_, c)| c.name.as_str()),
+            "c",
+            &[OBJECTIVE],
+        )
+    }
+
+    /// Writes the model in the CPLEX LP format. Names are sanitized, see [`Model::write`].
+    pub fn write_lp(&self, mut w: impl Write) -> io::Result<()> {
+        let vars = self.var_names();
+        let constrs = self.constr_names();
+        let expression = |w: &mut dyn Write, terms: &[(crate::model::Var, f64)]| {
+            if terms.is_empty() {
+                if let Some(v) = vars.first() {
+                    write!(w, " 0 {v}")?;
+                }
+            }
+            for (i, (v, c)) in terms.iter().enumerate() {
+                if i > 0 && i % TERMS_PER_LINE == 0 {
+                    write!(w, "\n  ")?;
+                }
+                let sign = if c.is_sign_negative() { '-' } else { '+' };
+                write!(w, " {sign} {} {}", number(c.abs()), vars[v.0])?;
+            }
+            Ok::<(), io::Error>(())
+        };
+
+        writeln!(w, "\\ Model {}", self.name())?;
+        let (objective, sense) = self.objective();
+        writeln!(
+            w,
+            "{}",
+            match sense {
+                ModelSense::Minimize => "Minimize",
+                ModelSense::Maximize => "Maximize",
+            }
+        )?;
+        write!(w, " {OBJECTIVE}:")?;
+        expression(&mut w, objective.terms())?;
+        if objective.constant() != 0.0 {
+            let c = objective.constant();
+            let sign = if c.is_sign_negative() { '-' } else { '+' };
+            write!(w, " {sign} {}", number(c.abs()))?;
+        }
+        writeln!(w)?;
+
+        writeln!(w, "Subject To")?;
+        for (name, (_, ConstrData { constraint, .. })) in constrs.iter().zip(self.constrs()) {
+            write!(w, " {name}:")?;
+            expression(&mut w, constraint.expr.terms())?;
+            let sense = match constraint.sense {
+                Sense::Less => "<=",
+                Sense::Greater => ">=",
+                Sense::Equal => "=",
+            };
+            writeln!(w, " {sense} {}", number(constraint.rhs))?;
+        }
+
+        writeln!(w, "Bounds")?;
+        for (name, (_, VarData { lb, ub, .. })) in vars.iter().zip(self.vars()) {
+            match (*lb, *ub) {
+                (lb, ub) if lb == 0.0 && ub == f64::INFINITY => {}
+                (f64::NEG_INFINITY, f64::INFINITY) => writeln!(w, " {name} free")?,
+                (lb, ub) if lb == ub => writeln!(w, " {name} = {}", number(lb))?,
+                (lb, ub) => writeln!(w, " {} <= {name} <= {}", number(lb), number(ub))?,
+            }
+        }
+
+        let integers = vars
+            .iter()
+            .zip(self.vars())
+            .filter(|(_, (_, v))| v.vtype == VarType::Integer)
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        if !integers.is_empty() {
+            writeln!(w, "General")?;
+            for name in integers {
+                writeln!(w, " {name}")?;
+            }
+        }
+        writeln!(w, "End")
+    }
+
+    /// Writes the model in the free MPS format. Names are sanitized, see [`Model::write`].
+    pub fn write_mps(&self, mut w: impl Write) -> io::Result<()> {
+        let vars = self.var_names();
+        let constrs = self.constr_names();
+        let (objective, sense) = self.objective();
+
+        writeln!(
+            w,
+            "NAME {}",
+            sanitize([self.name()].into_iter(), "model", &[])[0]
+        )?;
+        if sense == ModelSense::Maximize {
+            writeln!(w, "OBJSENSE\n    MAX")?;
+        }
+
+        writeln!(w, "ROWS")?;
+        writeln!(w, " N {OBJECTIVE}")?;
+        let mut columns = vec![Vec::new(); vars.len()];
+        for (v, c) in objective.terms() {
+            columns[v.0].push((OBJECTIVE, *c));
+        }
+        for (name, (_, ConstrData { constraint, .. })) in constrs.iter().zip(self.constrs()) {
+            let sense = match constraint.sense {
+                Sense::Less => 'L',
+                Sense::Greater => 'G',
+                Sense::Equal => 'E',
+            };
+            writeln!(w, " {sense} {name}")?;
+            for (v, c) in constraint.expr.terms() {
+                columns[v.0].push((name.as_str(), *c));
+            }
+        }
+
+        writeln!(w, "COLUMNS")?;
+        let mut integer = false;
+        for ((name, (_, var)), column) in vars.iter().zip(self.vars()).zip(columns.iter()) {
+            if (var.vtype == VarType::Integer) != integer {
+                integer = !integer;
+                let marker = if integer { "INTORG" } else { "INTEND" };
+                writeln!(w, " MARKER 'MARKER' '{marker}'")?;
+            }
+            if column.is_empty() {
+                writeln!(w, " {name} {OBJECTIVE} 0")?;
+            }
+            for (row, c) in column {
+                writeln!(w, " {name} {row} {}", number(*c))?;
+            }
+        }
+        if integer {
+            writeln!(w, " MARKER 'MARKER' 'INTEND'")?;
+        }
+
+        writeln!(w, "RHS")?;
+        if objective.constant() != 0.0 {
+            writeln!(w, " RHS {OBJECTIVE} {}", number(-objective.constant()))?;
+        }
+        for (name, (_, ConstrData { constraint, .. })) in constrs.iter().zip(self.constrs()) {
+            if constraint.rhs != 0.0 {
+                writeln!(w, " RHS {name} {}", number(constraint.rhs))?;
+            }
+        }
+
+        // Integer variables get explicit bounds as some readers default them to binary.
+        writeln!(w, "BOUNDS")?;
+        for (name, (_, VarData { vtype, lb, ub, .. })) in vars.iter().zip(self.vars()) {
+            match (*lb, *ub) {
+                (lb, ub) if lb == 0.0 && ub == f64::INFINITY && *vtype == VarType::Continuous => {}
+                (f64::NEG_INFINITY, f64::INFINITY) => writeln!(w, " FR BND {name}")?,
+                (lb, ub) if lb == ub => writeln!(w, " FX BND {name} {}", number(lb))?,
+                (lb, ub) => {
+                    if lb == f64::NEG_INFINITY {
+                        writeln!(w, " MI BND {name}")?;
+                    } else {
+                        writeln!(w, " LO BND {name} {}", number(lb))?;
+                    }
+                    if ub == f64::INFINITY {
+                        writeln!(w, " PL BND {name}")?;
+                    } else {
+                        writeln!(w, " UP BND {name} {}", number(ub))?;
+                    }
+                }
+            }
+        }
+        writeln!(w, "ENDATA")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{Model, ModelSense};
+
+    fn model() -> Model {
+        let mut model = Model::new("example");
+        let x = model.add_ctsvar("x", 0.0, f64::INFINITY).unwrap();
+        let y = model.add_intvar("y", f64::NEG_INFINITY, 4.0).unwrap();
+        let z = model.add_ctsvar("a(0)", 0.0, 1.0).unwrap();
+        model
+            .add_constr("dependency_a(0)_b(0)", (x - 2.0 * y).geq(z - 1.0))
+            .unwrap();
+        model
+            .add_constr("dependency_a(0)_b(0)", (x + y).leq(3.0))
+            .unwrap();
+        model.add_constr("", y.equals(x)).unwrap();
+        model
+            .set_objective(x + 2.0 * z, ModelSense::Maximize)
+            .unwrap();
+        model
+    }
+
+    #[test]
+    fn lp() {
+        let mut lp = Vec::new();
+        model().write_lp(&mut lp).unwrap();
+        assert_eq!(
+            String::from_utf8(lp).unwrap(),
+            "\\ Model example
+Maximize
+ obj: + 1 x + 2 a(0)
+Subject To
+ dependency_a(0)_b(0): + 1 x - 2 y - 1 a(0) >= -1
+ dependency_a(0)_b(0)_1: + 1 x + 1 y <= 3
+ c2: - 1 x + 1 y = 0
+Bounds
+ -inf <= y <= 4
+ 0 <= a(0) <= 1
+General
+ y
+End
+"
+        );
+    }
+
+    #[test]
+    fn mps() {
+        let mut mps = Vec::new();
+        model().write_mps(&mut mps).unwrap();
+        assert_eq!(
+            String::from_utf8(mps).unwrap(),
+            "NAME example
+OBJSENSE
+    MAX
+ROWS
+ N obj
+ G dependency_a(0)_b(0)
+ L dependency_a(0)_b(0)_1
+ E c2
+COLUMNS
+ x obj 1
+ x dependency_a(0)_b(0) 1
+ x dependency_a(0)_b(0)_1 1
+ x c2 -1
+ MARKER 'MARKER' 'INTORG'
+ y dependency_a(0)_b(0) -2
+ y dependency_a(0)_b(0)_1 1
+ y c2 1
+ MARKER 'MARKER' 'INTEND'
+ a(0) obj 2
+ a(0) dependency_a(0)_b(0) -1
+RHS
+ RHS dependency_a(0)_b(0) -1
+ RHS dependency_a(0)_b(0)_1 3
+BOUNDS
+ MI BND y
+ UP BND y 4
+ LO BND a(0) 0
+ UP BND a(0) 1
+ENDATA
+"
+        );
+    }
+}
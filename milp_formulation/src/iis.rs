@@ -0,0 +1,256 @@
+use crate::{
+    model::{Constr, Expr, Model, ModelSense, Var},
+    solver::{Solver, Status},
+    Dependency, ExecutionTimeT, MilpFormulation, NameT, Origin,
+};
+use mdsdf::vector::Vector;
+use std::{collections::BTreeMap, fmt};
+
+/// Irreducible infeasible subsystem of a [`Model`]: the model restricted to these constraints
+/// and bounds is infeasible, but dropping any one of them makes it feasible.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Iis {
+    pub constrs: Vec<Constr>,
+    pub lower_bounds: Vec<Var>,
+    pub upper_bounds: Vec<Var>,
+}
+
+enum Candidate {
+    Constr(Constr),
+    LowerBound(Var),
+    UpperBound(Var),
+}
+
+fn infeasible<S: Solver>(model: &Model) -> Result<bool, S::Error> {
+    let status = model.optimize::<S>()?.status;
+    Ok(matches!(
+        status,
+        Status::Infeasible | Status::InfeasibleOrUnbounded
+    ))
+}
+
+impl Model {
+    /// Finds an irreducible infeasible subsystem with a deletion filter: every constraint and
+    /// finite bound is dropped in turn and stays dropped if the model remains infeasible. This
+    /// works with any solver but optimizes the model once per constraint and bound.
+    ///
+    /// Returns `None` if the model is feasible.
+    pub fn iis<S: Solver>(&self) -> Result<Option<Iis>, S::Error> {
+        let mut model = self.clone();
+        model
+            .set_objective(Expr::default(), ModelSense::Minimize)
+            .unwrap();
+        if !infeasible::<S>(&model)? {
+            return Ok(None);
+        }
+
+        let candidates = self
+            .constrs()
+            .map(|(c, _)| Candidate::Constr(c))
+            .chain(self.vars().flat_map(|(v, data)| {
+                let lower = data.lb.is_finite().then_some(Candidate::LowerBound(v));
+                let upper = data.ub.is_finite().then_some(Candidate::UpperBound(v));
+                lower.into_iter().chain(upper)
+            }))
+            .collect::<Vec<_>>();
+
+        let mut iis = Iis::default();
+        for candidate in candidates {
+            let mut relaxed = model.clone();
+            match candidate {
+                Candidate::Constr(c) => relaxed.remove(c),
+                Candidate::LowerBound(v) => {
+                    let ub = relaxed.var(v).unwrap().ub;
+                    relaxed.set_bounds(v, f64::NEG_INFINITY, ub)
+                }
+                Candidate::UpperBound(v) => {
+                    let lb = relaxed.var(v).unwrap().lb;
+                    relaxed.set_bounds(v, lb, f64::INFINITY)
+                }
+            }
+            .unwrap();
+            if infeasible::<S>(&relaxed)? {
+                model = relaxed;
+            } else {
+                match candidate {
+                    Candidate::Constr(c) => iis.constrs.push(c),
+                    Candidate::LowerBound(v) => iis.lower_bounds.push(v),
+                    Candidate::UpperBound(v) => iis.upper_bounds.push(v),
+                }
+            }
+        }
+        Ok(Some(iis))
+    }
+}
+
+/// Cause of the infeasibility of a [`MilpFormulation`], see [`MilpFormulation::diagnose`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict<const N: usize> {
+    /// Constraints of the formulation and its extensions.
+    pub origins: Vec<Origin<N>>,
+    /// Names of the other constraints, e.g. added by the caller to share memories.
+    pub constraints: Vec<String>,
+    /// Names of the variables whose lower bound is part of the conflict.
+    pub lower_bounds: Vec<String>,
+    /// Names of the variables whose upper bound is part of the conflict, e.g. buffer sizes.
+    pub upper_bounds: Vec<String>,
+    /// Names of the HSDF actors that appear in `origins`.
+    pub names: BTreeMap<(usize, Vector<N, usize>), String>,
+}
+
+impl<const N: usize> fmt::Display for Conflict<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |a: &(usize, Vector<N, usize>)| self.names[a].as_str();
+        for origin in self.origins.iter() {
+            match origin {
+                Origin::Dependency(Dependency {
+                    channel,
+                    source,
+                    target,
+                    dimension,
+                    buffer: false,
+                }) => writeln!(
+                    f,
+                    "channel {channel} from {} to {} in dimension {dimension}",
+                    name(source),
+                    name(target)
+                )?,
+                Origin::Dependency(Dependency {
+                    channel,
+                    source,
+                    target,
+                    dimension,
+                    buffer: true,
+                }) => writeln!(
+                    f,
+                    "buffer of channel {channel} from {} back to {} in dimension {dimension}",
+                    name(source),
+                    name(target)
+                )?,
+                Origin::BufferCapacity { channel, dimension } => writeln!(
+                    f,
+                    "capacity of the buffer of channel {channel} in dimension {dimension}"
+                )?,
+                Origin::ProcessorExclusion {
+                    processor,
+                    first,
+                    second,
+                } if first == second => {
+                    writeln!(f, "{} alone on processor {processor}", name(first))?
+                }
+                Origin::ProcessorExclusion {
+                    processor,
+                    first,
+                    second,
+                } => writeln!(
+                    f,
+                    "{} and {} sharing processor {processor}",
+                    name(first),
+                    name(second)
+                )?,
+                Origin::ProcessorLoad { processor } => {
+                    writeln!(f, "load of processor {processor}")?
+                }
+                Origin::Latency {
+                    source,
+                    target,
+                    dimension,
+                } => writeln!(
+                    f,
+                    "latency from {} to {} in dimension {dimension}",
+                    name(source),
+                    name(target)
+                )?,
+                Origin::Periodicity { actor, iterations } => writeln!(
+                    f,
+                    "{} repeating the firing {iterations} iterations earlier",
+                    name(actor)
+                )?,
+            }
+        }
+        for c in self.constraints.iter() {
+            writeln!(f, "constraint {c}")?;
+        }
+        for v in self.lower_bounds.iter() {
+            writeln!(f, "lower bound of {v}")?;
+        }
+        for v in self.upper_bounds.iter() {
+            writeln!(f, "upper bound of {v}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>
+    MilpFormulation<'a, N, ExecutionTime, Name>
+{
+    /// Explains why `self.model` is infeasible from an irreducible infeasible subsystem, see
+    /// [`Model::iis`]. Returns `None` if the model is feasible.
+    pub fn diagnose<S: Solver>(&mut self) -> Result<Option<Conflict<N>>, S::Error> {
+        let Some(iis) = self.model.iis::<S>()? else {
+            return Ok(None);
+        };
+
+        let mut conflict = Conflict {
+            origins: Vec::new(),
+            constraints: Vec::new(),
+            lower_bounds: Vec::new(),
+            upper_bounds: Vec::new(),
+            names: BTreeMap::new(),
+        };
+        for c in iis.constrs {
+            match self.origins.get(&c) {
+                Some(origin) => conflict.origins.push(*origin),
+                None => conflict
+                    .constraints
+                    .push(self.model.constr(c).unwrap().name.clone()),
+            }
+        }
+        let var_name = |v: Var| self.model.var(v).unwrap().name.clone();
+        conflict.lower_bounds = iis.lower_bounds.into_iter().map(var_name).collect();
+        conflict.upper_bounds = iis.upper_bounds.into_iter().map(var_name).collect();
+
+        for origin in conflict.origins.iter() {
+            let actors = match origin {
+                Origin::Dependency(Dependency { source, target, .. }) => vec![*source, *target],
+                Origin::ProcessorExclusion { first, second, .. } => vec![*first, *second],
+                Origin::Latency { source, target, .. } => vec![*source, *target],
+                Origin::Periodicity { actor, .. } => vec![*actor],
+                Origin::BufferCapacity { .. } | Origin::ProcessorLoad { .. } => Vec::new(),
+            };
+            for a in actors {
+                conflict.names.entry(a).or_insert_with(|| (self.name)(a));
+            }
+        }
+        Ok(Some(conflict))
+    }
+}
+
+#[cfg(all(test, any(feature = "microlp", feature = "grb")))]
+mod tests {
+    use super::*;
+    use crate::solver::DefaultSolver;
+
+    #[test]
+    fn deletion_filter() {
+        let mut model = Model::new("model");
+        let x = model.add_ctsvar("x", 0.0, 5.0).unwrap();
+        let y = model.add_intvar("y", 0.0, f64::INFINITY).unwrap();
+        model.add_constr("c0", (x + y).leq(100.0)).unwrap();
+        let c1 = model.add_constr("c1", (x - y).geq(4.5)).unwrap();
+        let c2 = model.add_constr("c2", y.geq(1.0)).unwrap();
+        model.add_constr("c3", x.geq(1.0)).unwrap();
+
+        assert_eq!(
+            model.iis::<DefaultSolver>().unwrap(),
+            Some(Iis {
+                constrs: vec![c1, c2],
+                lower_bounds: vec![],
+                upper_bounds: vec![x],
+            })
+        );
+
+        model.remove(c2).unwrap();
+        assert_eq!(model.iis::<DefaultSolver>().unwrap(), None);
+    }
+}
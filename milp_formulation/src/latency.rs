@@ -0,0 +1,152 @@
+use crate::{
+    model::{self, Constr, Expr, ModelSense},
+    ExecutionTimeT, MilpFormulation, NameT, Origin,
+};
+use mdsdf::vector::Vector;
+
+impl<'a, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>
+    MilpFormulation<'a, N, ExecutionTime, Name>
+{
+    /// Bounds the time from the start of `source` to the end of `target` `iterations`
+    /// iterations of `self.hsdf` later in `dimension` by `deadline`. Both sides are multiplied
+    /// by the throughput, so the constraint stays linear in the offsets.
+    pub fn add_latency_constraint(
+        &mut self,
+        source: (usize, Vector<N, usize>),
+        target: (usize, Vector<N, usize>),
+        iterations: usize,
+        dimension: usize,
+        deadline: f64,
+    ) -> model::Result<Constr> {
+        let throughput = self.throughputs[dimension];
+        let e = (self.execution_time)(target) as f64;
+        let latency = self.u[&target] - self.u[&source] + iterations as f64 + e * throughput;
+        let constr = self.model.add_constr(
+            &format!(
+                "latency_{dimension}_{}_{}",
+                (self.name)(source),
+                (self.name)(target)
+            ),
+            latency.leq(deadline * throughput),
+        )?;
+        self.origins.insert(
+            constr,
+            Origin::Latency {
+                source,
+                target,
+                dimension,
+            },
+        );
+        Ok(constr)
+    }
+
+    /// Latency of [`MilpFormulation::add_latency_constraint`], which is only linear once the
+    /// throughput of `dimension` is fixed to `throughput`.
+    pub fn latency(
+        &mut self,
+        source: (usize, Vector<N, usize>),
+        target: (usize, Vector<N, usize>),
+        iterations: usize,
+        throughput: f64,
+    ) -> Expr {
+        let e = (self.execution_time)(target) as f64;
+        (self.u[&target] - self.u[&source] + iterations as f64) * (1.0 / throughput) + e
+    }
+
+    /// Fixes the throughput of `dimension` to `throughput` and makes minimizing the
+    /// [`MilpFormulation::latency`] the objective. Returns the constraint fixing the throughput,
+    /// which can be removed to optimize it again.
+    pub fn minimize_latency(
+        &mut self,
+        source: (usize, Vector<N, usize>),
+        target: (usize, Vector<N, usize>),
+        iterations: usize,
+        dimension: usize,
+        throughput: f64,
+    ) -> model::Result<Constr> {
+        let constr = self.model.add_constr(
+            &format!("fixed_throughput_{dimension}"),
+            self.throughputs[dimension].equals(throughput),
+        )?;
+        let latency = self.latency(source, target, iterations, throughput);
+        self.model.set_objective(latency, ModelSense::Minimize)?;
+        Ok(constr)
+    }
+}
+
+#[cfg(all(test, any(feature = "microlp", feature = "grb")))]
+mod tests {
+    use crate::{
+        model::ModelSense,
+        solver::{DefaultSolver, Status},
+        MilpFormulation, Origin,
+    };
+    use mdsdf::{vector::Vector, Channel, Mdsdf};
+    use std::borrow::Cow;
+
+    #[test]
+    fn sensor_to_actuator() {
+        let mut sdf = Mdsdf::<1>::new(3);
+        for (source, target, initial_tokens) in [
+            (0, 1, 0),
+            (1, 2, 0),
+            (2, 0, 2),
+            (0, 0, 1),
+            (1, 1, 1),
+            (2, 2, 1),
+        ] {
+            sdf.add_channel(Channel {
+                production_rate: [1].into(),
+                consumption_rate: [1].into(),
+                source,
+                target,
+                initial_tokens: [initial_tokens].into(),
+            });
+        }
+        let hsdf = sdf.hsdf();
+        let mut milp = MilpFormulation::new(
+            Cow::Borrowed(&hsdf),
+            |(a, _): (usize, Vector<1, usize>)| [1, 2, 3][a],
+            |(a, _)| ["sensor", "filter", "actuator"][a].to_string(),
+        )
+        .unwrap();
+        let (sensor, actuator) = ((0, [0].into()), (2, [0].into()));
+
+        // the chain takes 6, and the actuator of the next iteration has to wait for the
+        // sensor of that iteration, one period of 4 later
+        for (iterations, latency) in [(0, 6.0), (1, 10.0)] {
+            let fixed = milp
+                .minimize_latency(sensor, actuator, iterations, 0, 0.25)
+                .unwrap();
+            let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+            assert!((solution.objective().unwrap() - latency).abs() < 1e-6);
+            milp.model.remove(fixed).unwrap();
+        }
+
+        milp.add_latency_constraint(sensor, actuator, 0, 0, 8.0)
+            .unwrap();
+        milp.model
+            .set_objective(milp.throughputs[0], ModelSense::Maximize)
+            .unwrap();
+        let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+        assert!((solution.value(milp.throughputs[0]).unwrap() - 1.0 / 3.0).abs() < 1e-6);
+
+        // a deadline shorter than the chain only holds when nothing runs
+        let deadline = milp
+            .add_latency_constraint(sensor, actuator, 0, 0, 5.0)
+            .unwrap();
+        let throughput = milp.throughputs[0];
+        milp.model
+            .add_constr("min_throughput", throughput.geq(0.25))
+            .unwrap();
+        let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+        assert_eq!(solution.status, Status::Infeasible);
+        let conflict = milp.diagnose::<DefaultSolver>().unwrap().unwrap();
+        assert!(conflict.origins.contains(&milp.origins[&deadline]));
+        assert!(matches!(
+            milp.origins[&deadline],
+            Origin::Latency { dimension: 0, .. }
+        ));
+        assert_eq!(conflict.constraints, ["min_throughput"]);
+    }
+}
@@ -1,18 +1,82 @@
 #![feature(iterator_try_collect)]
 #![feature(trait_alias)]
 
-use mdsdf::{vector::Vector, Hsdf, HsdfChannel};
-use std::{borrow::Cow, collections::BTreeMap};
+pub mod bottleneck;
+mod export;
+pub mod iis;
+mod latency;
+pub mod model;
+pub mod schedule;
+pub mod solver;
+
+use mdsdf::{vector::Vector, ChannelIndex, Hsdf, HsdfChannel};
+use model::{Constr, Model, Var};
+use std::{borrow::Cow, collections::BTreeMap, num::NonZeroUsize};
 
 pub trait ExecutionTimeT<const N: usize> = FnMut((usize, Vector<N, usize>)) -> usize;
 
 pub trait NameT<const N: usize> = FnMut((usize, Vector<N, usize>)) -> String;
 
+/// Precedence between two HSDF actors that a constraint of a [`MilpFormulation`] enforces:
+/// `target` cannot start before `source` has finished, offset by the tokens on the edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dependency<const N: usize> {
+    pub channel: ChannelIndex,
+    pub source: (usize, Vector<N, usize>),
+    pub target: (usize, Vector<N, usize>),
+    pub dimension: usize,
+    /// The dependency goes against `channel` and models the capacity of its buffer, so
+    /// `source` is the consumer of `channel`.
+    pub buffer: bool,
+}
+
+/// What a constraint of a [`MilpFormulation`] models, for constraints added by this crate and
+/// the ones that extend it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin<const N: usize> {
+    Dependency(Dependency<N>),
+    /// Bounds the tokens in the buffer of `channel` by its capacity.
+    BufferCapacity {
+        channel: ChannelIndex,
+        dimension: usize,
+    },
+    /// Keeps `first` and `second` from overlapping on `processor`. Both are the same actor
+    /// when it is alone on the processor and only has to finish before its next iteration.
+    ProcessorExclusion {
+        processor: usize,
+        first: (usize, Vector<N, usize>),
+        second: (usize, Vector<N, usize>),
+    },
+    /// Bounds the total execution time of the actors on `processor` by the period.
+    ProcessorLoad {
+        processor: usize,
+    },
+    /// Bounds the time from the start of `source` to the end of `target`, see
+    /// [`MilpFormulation::add_latency_constraint`].
+    Latency {
+        source: (usize, Vector<N, usize>),
+        target: (usize, Vector<N, usize>),
+        dimension: usize,
+    },
+    /// Starts `actor` `iterations` iterations after the firing it repeats in a K-periodic
+    /// schedule, see [`MilpFormulation::k_periodic`].
+    Periodicity {
+        actor: (usize, Vector<N, usize>),
+        iterations: usize,
+    },
+}
+
 pub struct MilpFormulation<'a, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>> {
     pub hsdf: Cow<'a, Hsdf<'a, N>>,
-    pub model: grb::Model,
-    pub u: BTreeMap<(usize, Vector<N, usize>), grb::Var>,
-    pub throughputs: Vec<grb::Var>,
+    pub model: Model,
+    pub u: BTreeMap<(usize, Vector<N, usize>), Var>,
+    /// Iterations of `hsdf` per time unit in every dimension.
+    pub throughputs: Vec<Var>,
+    /// Iterations of the original graph in one iteration of `hsdf`, more than one when it was
+    /// unfolded for a K-periodic schedule. The throughput of the graph is then this many times
+    /// `throughputs`.
+    pub iterations: usize,
+    pub origins: BTreeMap<Constr, Origin<N>>,
     pub execution_time: ExecutionTime,
     pub name: Name,
 }
@@ -20,61 +84,137 @@ pub struct MilpFormulation<'a, const N: usize, ExecutionTime: ExecutionTimeT<N>,
 impl<'a, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>
     MilpFormulation<'a, N, ExecutionTime, Name>
 {
+    /// Formulation of a strictly periodic schedule, in which every HSDF actor starts once per
+    /// period.
     pub fn new(
         hsdf: Cow<'a, Hsdf<'a, N>>,
+        execution_time: ExecutionTime,
+        name: Name,
+    ) -> model::Result<Self> {
+        Self::k_periodic(hsdf, |_| NonZeroUsize::MIN, execution_time, name)
+    }
+
+    /// Formulation of a K-periodic schedule, in which actor `a` of the graph repeats its
+    /// firings every `periodicity(a)` iterations instead of every iteration. `hsdf` is unfolded
+    /// into the least common multiple of the periodicities, see [`Hsdf::unfold`], so every
+    /// firing in those iterations gets its own start offset, and the offsets of later
+    /// iterations of `a` are tied to its first `periodicity(a)` ones.
+    ///
+    /// Dependencies alone never need more than one offset per HSDF actor, but the constraints
+    /// that extensions add, like those of a cyclic schedule, can reach a higher throughput with
+    /// larger periodicities, at the cost of a larger model. `execution_time` and `name` get the
+    /// actors of the unfolded graph.
+    pub fn k_periodic(
+        hsdf: Cow<'a, Hsdf<'a, N>>,
+        mut periodicity: impl FnMut(usize) -> NonZeroUsize,
         mut execution_time: ExecutionTime,
         mut name: Name,
-    ) -> grb::Result<Self> {
-        use grb::prelude::*;
+    ) -> model::Result<Self> {
+        let periodicities = (0..hsdf.mdsdf.n_actors())
+            .map(|a| periodicity(a).get())
+            .collect::<Vec<_>>();
+        let iterations = periodicities.iter().fold(1, |a, b| lcm(a, *b));
+        let repetition_vector = hsdf.repetition_vector.clone();
+        let hsdf = match iterations {
+            1 => hsdf,
+            _ => Cow::Owned(hsdf.unfold(iterations)),
+        };
 
-        let mut model = Model::new("model")?;
+        let mut model = Model::new("model");
 
         let throughputs = (0..N)
-            .map(|i| add_ctsvar!(model, name: &format!("throughput_{i}"), bounds: 0.0..))
+            .map(|i| model.add_ctsvar(&format!("throughput_{i}"), 0.0, f64::INFINITY))
             .try_collect::<Vec<_>>()?;
 
         let u: BTreeMap<(usize, Vector<N, usize>), Var> = hsdf
             .actors()
-            .map(|a| add_ctsvar!(model, name: &name(a), bounds: 0.0..).map(|v| (a, v)))
+            .map(|a| {
+                model
+                    .add_ctsvar(&name(a), 0.0, f64::INFINITY)
+                    .map(|v| (a, v))
+            })
             .try_collect()?;
 
+        let mut origins = BTreeMap::new();
         for HsdfChannel {
+            channel,
             source,
             target,
             initial_tokens,
+            ..
         } in hsdf.channels()
         {
             let u_source = u.get(&source).unwrap();
             let u_target = u.get(&target).unwrap();
             for i in 0..N {
-                let u_source = u_source.clone();
-                let u_target = u_target.clone();
-                let e = execution_time(source);
-                let throughput = throughputs[i].clone();
+                let e = execution_time(source) as f64;
                 let initial_tokens = initial_tokens[i] as f64;
-                model.add_constr(
-                    &format!("dependency_{}_{}", name(source), name(target)),
-                    c!(u_target >= u_source + e * throughput - initial_tokens),
+                let constr = model.add_constr(
+                    &format!("dependency_{channel}_{i}_{}_{}", name(source), name(target)),
+                    u_target.geq(*u_source + e * throughputs[i] - initial_tokens),
                 )?;
+                origins.insert(
+                    constr,
+                    Origin::Dependency(Dependency {
+                        channel,
+                        source,
+                        target,
+                        dimension: i,
+                        buffer: false,
+                    }),
+                );
             }
         }
 
+        for (a, i) in hsdf.actors() {
+            let k = periodicities[a];
+            // the firing `k` iterations earlier, in the first dimension that has one
+            let Some(d) = (0..N).find(|d| i[*d] >= k * repetition_vector[a][*d]) else {
+                continue;
+            };
+            let mut earlier = i;
+            earlier[d] -= k * repetition_vector[a][d];
+            let constr = model.add_constr(
+                &format!("periodicity_{}", name((a, i))),
+                u[&(a, i)].equals(u[&(a, earlier)] + k as f64 / iterations as f64),
+            )?;
+            origins.insert(
+                constr,
+                Origin::Periodicity {
+                    actor: (a, i),
+                    iterations: k,
+                },
+            );
+        }
+
         Ok(Self {
             hsdf,
             model,
             u,
             throughputs,
+            iterations,
+            origins,
             name,
             execution_time,
         })
     }
 }
 
-#[cfg(test)]
+fn lcm(a: usize, b: usize) -> usize {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    a / x * b
+}
+
+#[cfg(all(test, any(feature = "microlp", feature = "grb")))]
 mod tests {
 
     use super::*;
     use mdsdf::{Channel, Mdsdf};
+    use model::ModelSense;
+    use solver::{DefaultSolver, Status};
 
     #[test]
     fn test() {
@@ -138,8 +278,102 @@ mod tests {
         .unwrap();
         milp_formulation
             .model
-            .set_objective(milp_formulation.throughputs[0], grb::ModelSense::Maximize)
+            .set_objective(milp_formulation.throughputs[0], ModelSense::Maximize)
+            .unwrap();
+        let solution = milp_formulation.model.optimize::<DefaultSolver>().unwrap();
+        assert_eq!(solution.status, Status::Optimal);
+        let throughput = solution.value(milp_formulation.throughputs[0]).unwrap();
+        assert!((throughput - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn csdf() {
+        use mdsdf::csdf::{Csdf, CsdfChannel};
+
+        // a source feeding a three phase decoder that only reads in its first phase
+        let mut csdf = Csdf::<1>::new(vec![3, 1, 2]);
+        for (source, target, production_rates, consumption_rates, initial_tokens) in [
+            (1, 0, vec![1], vec![1, 0, 0], 0),
+            (0, 2, vec![1, 1, 1], vec![2, 1], 0),
+            (2, 1, vec![0, 1], vec![1], 1),
+            (0, 0, vec![1, 1, 1], vec![1, 1, 1], 1),
+            (1, 1, vec![1], vec![1], 1),
+            (2, 2, vec![1, 1], vec![1, 1], 1),
+        ] {
+            csdf.add_channel(CsdfChannel {
+                production_rates: production_rates.into_iter().map(|r| [r].into()).collect(),
+                consumption_rates: consumption_rates.into_iter().map(|r| [r].into()).collect(),
+                source,
+                target,
+                initial_tokens: [initial_tokens].into(),
+            });
+        }
+
+        let hsdf = csdf.hsdf();
+        let mut milp = MilpFormulation::new(
+            Cow::Borrowed(&hsdf),
+            |(a, _)| [1, 2, 3][a],
+            |(a, i)| format!("{}({})", ["decoder", "source", "sink"][a], i[0]),
+        )
+        .unwrap();
+        milp.model
+            .set_objective(milp.throughputs[0], ModelSense::Maximize)
+            .unwrap();
+        let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+        let throughput = solution.value(milp.throughputs[0]).unwrap();
+        assert!((throughput - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn k_periodic() {
+        let mut sdf = Mdsdf::<1>::new(3);
+        for (source, target, production_rate, consumption_rate, initial_tokens) in [
+            (0, 1, 2, 3, 0),
+            (1, 2, 1, 2, 0),
+            (0, 0, 1, 1, 1),
+            (1, 1, 1, 1, 1),
+            (2, 2, 1, 1, 1),
+        ] {
+            sdf.add_channel(Channel {
+                production_rate: [production_rate].into(),
+                consumption_rate: [consumption_rate].into(),
+                source,
+                target,
+                initial_tokens: [initial_tokens].into(),
+            });
+        }
+
+        let hsdf = sdf.hsdf();
+        let mut milp = MilpFormulation::k_periodic(
+            Cow::Borrowed(&hsdf),
+            |a| NonZeroUsize::new([2, 1, 1][a]).unwrap(),
+            |(a, _)| [1, 2, 2][a],
+            |(a, i)| format!("{}({})", ["a", "b", "c"][a], i[0]),
+        )
+        .unwrap();
+        assert_eq!(milp.iterations, 2);
+        assert_eq!(milp.u.len(), 12);
+        milp.model
+            .set_objective(milp.throughputs[0], ModelSense::Maximize)
             .unwrap();
-        milp_formulation.model.optimize().unwrap();
+        let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+        let throughput = solution.value(milp.throughputs[0]).unwrap();
+        assert!((throughput * milp.iterations as f64 - 0.25).abs() < 1e-6);
+        let schedule = milp.schedule(&solution).unwrap();
+        assert_eq!(schedule.iterations, 2);
+        assert!((schedule.throughput()[0] - 0.25).abs() < 1e-6);
+
+        // b repeats every iteration, half of an unfolded one, while a may differ
+        let offset = |a: (usize, Vector<1, usize>)| solution.value(milp.u[&a]).unwrap();
+        for i in 0..2 {
+            let shift = offset((1, [i + 2].into())) - offset((1, [i].into()));
+            assert!((shift - 0.5).abs() < 1e-6);
+        }
+        let periodicity = milp
+            .origins
+            .values()
+            .filter(|o| matches!(o, Origin::Periodicity { .. }))
+            .count();
+        assert_eq!(periodicity, 3);
     }
 }
@@ -0,0 +1,424 @@
+use crate::solver::{Solution, Solver, Status};
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Mul, Neg, Sub},
+};
+
+/// Variable of a [`Model`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Var(pub(crate) usize);
+
+/// Constraint of a [`Model`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Constr(pub(crate) usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarType {
+    Continuous,
+    Integer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sense {
+    Less,
+    Greater,
+    Equal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelSense {
+    Minimize,
+    Maximize,
+}
+
+/// Affine expression over the variables of a [`Model`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Expr {
+    terms: Vec<(Var, f64)>,
+    constant: f64,
+}
+
+impl Expr {
+    pub fn terms(&self) -> &[(Var, f64)] {
+        &self.terms
+    }
+
+    pub fn constant(&self) -> f64 {
+        self.constant
+    }
+
+    pub fn leq(self, rhs: impl Into<Expr>) -> Constraint {
+        Constraint::new(self - rhs.into(), Sense::Less)
+    }
+
+    pub fn geq(self, rhs: impl Into<Expr>) -> Constraint {
+        Constraint::new(self - rhs.into(), Sense::Greater)
+    }
+
+    pub fn equals(self, rhs: impl Into<Expr>) -> Constraint {
+        Constraint::new(self - rhs.into(), Sense::Equal)
+    }
+
+    /// Merges the terms of every variable, dropping those that cancel out.
+    fn simplify(mut self) -> Self {
+        self.terms.sort_by_key(|(v, _)| *v);
+        let mut terms: Vec<(Var, f64)> = Vec::with_capacity(self.terms.len());
+        for (v, c) in self.terms {
+            match terms.last_mut() {
+                Some((last, coefficient)) if *last == v => *coefficient += c,
+                _ => terms.push((v, c)),
+            }
+        }
+        terms.retain(|(_, c)| *c != 0.0);
+        Self {
+            terms,
+            constant: self.constant,
+        }
+    }
+}
+
+impl Var {
+    pub fn leq(self, rhs: impl Into<Expr>) -> Constraint {
+        Expr::from(self).leq(rhs)
+    }
+
+    pub fn geq(self, rhs: impl Into<Expr>) -> Constraint {
+        Expr::from(self).geq(rhs)
+    }
+
+    pub fn equals(self, rhs: impl Into<Expr>) -> Constraint {
+        Expr::from(self).equals(rhs)
+    }
+}
+
+impl From<Var> for Expr {
+    fn from(v: Var) -> Self {
+        Self {
+            terms: vec![(v, 1.0)],
+            constant: 0.0,
+        }
+    }
+}
+
+impl From<f64> for Expr {
+    fn from(constant: f64) -> Self {
+        Self {
+            terms: Vec::new(),
+            constant,
+        }
+    }
+}
+
+impl<T: Into<Expr>> AddAssign<T> for Expr {
+    fn add_assign(&mut self, rhs: T) {
+        let Expr { terms, constant } = rhs.into();
+        self.terms.extend(terms);
+        self.constant += constant;
+    }
+}
+
+impl<T: Into<Expr>> Add<T> for Expr {
+    type Output = Expr;
+    fn add(mut self, rhs: T) -> Expr {
+        self += rhs;
+        self
+    }
+}
+
+impl<T: Into<Expr>> Sub<T> for Expr {
+    type Output = Expr;
+    fn sub(self, rhs: T) -> Expr {
+        self + -rhs.into()
+    }
+}
+
+impl<T: Into<Expr>> Add<T> for Var {
+    type Output = Expr;
+    fn add(self, rhs: T) -> Expr {
+        Expr::from(self) + rhs
+    }
+}
+
+impl<T: Into<Expr>> Sub<T> for Var {
+    type Output = Expr;
+    fn sub(self, rhs: T) -> Expr {
+        Expr::from(self) - rhs
+    }
+}
+
+impl Mul<f64> for Expr {
+    type Output = Expr;
+    fn mul(self, rhs: f64) -> Expr {
+        Expr {
+            terms: self.terms.into_iter().map(|(v, c)| (v, c * rhs)).collect(),
+            constant: self.constant * rhs,
+        }
+    }
+}
+
+impl Mul<f64> for Var {
+    type Output = Expr;
+    fn mul(self, rhs: f64) -> Expr {
+        Expr::from(self) * rhs
+    }
+}
+
+impl Mul<Expr> for f64 {
+    type Output = Expr;
+    fn mul(self, rhs: Expr) -> Expr {
+        rhs * self
+    }
+}
+
+impl Mul<Var> for f64 {
+    type Output = Expr;
+    fn mul(self, rhs: Var) -> Expr {
+        rhs * self
+    }
+}
+
+impl Neg for Expr {
+    type Output = Expr;
+    fn neg(self) -> Expr {
+        self * -1.0
+    }
+}
+
+impl Neg for Var {
+    type Output = Expr;
+    fn neg(self) -> Expr {
+        Expr::from(self) * -1.0
+    }
+}
+
+impl<T: Into<Expr>> Sum<T> for Expr {
+    fn sum<I: Iterator<Item = T>>(iter: I) -> Self {
+        iter.fold(Expr::default(), |a, b| a + b)
+    }
+}
+
+/// Linear constraint `expr sense rhs`, with all variables on the left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraint {
+    pub expr: Expr,
+    pub sense: Sense,
+    pub rhs: f64,
+}
+
+impl Constraint {
+    fn new(mut expr: Expr, sense: Sense) -> Self {
+        let rhs = -expr.constant;
+        expr.constant = 0.0;
+        Self { expr, sense, rhs }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarData {
+    pub name: String,
+    pub vtype: VarType,
+    pub lb: f64,
+    pub ub: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstrData {
+    pub name: String,
+    pub constraint: Constraint,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The variable does not belong to the model.
+    UnknownVar(Var),
+    /// The constraint does not belong to the model or was removed.
+    UnknownConstr(Constr),
+    /// The bounds of the variable with this name are empty or not a number.
+    InvalidBounds(String),
+    /// A coefficient or right hand side of the expression with this name is not finite.
+    NotFinite(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownVar(Var(v)) => write!(f, "variable {v} does not belong to the model"),
+            Self::UnknownConstr(Constr(c)) => {
+                write!(f, "constraint {c} does not belong to the model")
+            }
+            Self::InvalidBounds(name) => write!(f, "variable {name} has invalid bounds"),
+            Self::NotFinite(name) => write!(f, "{name} has a coefficient that is not finite"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Mixed integer linear program, independent of the [`Solver`] used to optimize it.
+#[derive(Debug, Clone)]
+pub struct Model {
+    name: String,
+    vars: Vec<VarData>,
+    constrs: Vec<Option<ConstrData>>,
+    objective: Expr,
+    sense: ModelSense,
+}
+
+impl Model {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            vars: Vec::new(),
+            constrs: Vec::new(),
+            objective: Expr::default(),
+            sense: ModelSense::Minimize,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn add_var(&mut self, name: &str, vtype: VarType, lb: f64, ub: f64) -> Result<Var> {
+        if lb.is_nan() || ub.is_nan() || lb > ub {
+            return Err(Error::InvalidBounds(name.to_string()));
+        }
+        self.vars.push(VarData {
+            name: name.to_string(),
+            vtype,
+            lb,
+            ub,
+        });
+        Ok(Var(self.vars.len() - 1))
+    }
+
+    pub fn add_ctsvar(&mut self, name: &str, lb: f64, ub: f64) -> Result<Var> {
+        self.add_var(name, VarType::Continuous, lb, ub)
+    }
+
+    pub fn add_intvar(&mut self, name: &str, lb: f64, ub: f64) -> Result<Var> {
+        self.add_var(name, VarType::Integer, lb, ub)
+    }
+
+    pub fn set_bounds(&mut self, var: Var, lb: f64, ub: f64) -> Result<()> {
+        let data = self.vars.get_mut(var.0).ok_or(Error::UnknownVar(var))?;
+        if lb.is_nan() || ub.is_nan() || lb > ub {
+            return Err(Error::InvalidBounds(data.name.clone()));
+        }
+        data.lb = lb;
+        data.ub = ub;
+        Ok(())
+    }
+
+    pub fn add_constr(&mut self, name: &str, constraint: Constraint) -> Result<Constr> {
+        let expr = self.check(name, constraint.expr)?;
+        if constraint.rhs.is_nan() {
+            return Err(Error::NotFinite(name.to_string()));
+        }
+        self.constrs.push(Some(ConstrData {
+            name: name.to_string(),
+            constraint: Constraint { expr, ..constraint },
+        }));
+        Ok(Constr(self.constrs.len() - 1))
+    }
+
+    pub fn remove(&mut self, constr: Constr) -> Result<()> {
+        match self.constrs.get_mut(constr.0) {
+            Some(c @ Some(_)) => {
+                *c = None;
+                Ok(())
+            }
+            _ => Err(Error::UnknownConstr(constr)),
+        }
+    }
+
+    pub fn set_objective(&mut self, expr: impl Into<Expr>, sense: ModelSense) -> Result<()> {
+        self.objective = self.check("objective", expr.into())?;
+        self.sense = sense;
+        Ok(())
+    }
+
+    pub fn objective(&self) -> (&Expr, ModelSense) {
+        (&self.objective, self.sense)
+    }
+
+    pub fn var(&self, var: Var) -> Result<&VarData> {
+        self.vars.get(var.0).ok_or(Error::UnknownVar(var))
+    }
+
+    pub fn vars(&self) -> impl Iterator<Item = (Var, &VarData)> {
+        self.vars.iter().enumerate().map(|(i, v)| (Var(i), v))
+    }
+
+    pub fn constr(&self, constr: Constr) -> Result<&ConstrData> {
+        self.constrs
+            .get(constr.0)
+            .and_then(Option::as_ref)
+            .ok_or(Error::UnknownConstr(constr))
+    }
+
+    /// Constraints that were not removed, in the order they were added.
+    pub fn constrs(&self) -> impl Iterator<Item = (Constr, &ConstrData)> {
+        self.constrs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| c.as_ref().map(|c| (Constr(i), c)))
+    }
+
+    /// Optimizes the model with a new instance of `S`.
+    pub fn optimize<S: Solver>(&self) -> std::result::Result<Solution, S::Error> {
+        self.optimize_with(S::new(&self.name)?)
+    }
+
+    /// Optimizes the model with `solver`, which may have been configured beforehand.
+    pub fn optimize_with<S: Solver>(
+        &self,
+        mut solver: S,
+    ) -> std::result::Result<Solution, S::Error> {
+        let vars = self
+            .vars
+            .iter()
+            .map(|v| solver.add_var(&v.name, v.vtype, v.lb, v.ub))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let terms = |e: &Expr| {
+            e.terms
+                .iter()
+                .map(|(v, c)| (vars[v.0].clone(), *c))
+                .collect::<Vec<_>>()
+        };
+        for (_, ConstrData { name, constraint }) in self.constrs() {
+            solver.add_constr(
+                name,
+                &terms(&constraint.expr),
+                constraint.sense,
+                constraint.rhs,
+            )?;
+        }
+        solver.set_objective(&terms(&self.objective), self.objective.constant, self.sense)?;
+
+        let status = solver.optimize()?;
+        let values = if status == Status::Optimal {
+            vars.iter()
+                .map(|v| solver.value(v))
+                .collect::<std::result::Result<Vec<_>, _>>()?
+        } else {
+            Vec::new()
+        };
+        Ok(Solution::new(status, values, &self.objective))
+    }
+
+    /// Checks that `expr` only uses variables of this model and finite coefficients.
+    fn check(&self, name: &str, expr: Expr) -> Result<Expr> {
+        let expr = expr.simplify();
+        if let Some((v, _)) = expr.terms.iter().find(|(v, _)| v.0 >= self.vars.len()) {
+            return Err(Error::UnknownVar(*v));
+        }
+        if expr.terms.iter().any(|(_, c)| !c.is_finite()) || !expr.constant.is_finite() {
+            return Err(Error::NotFinite(name.to_string()));
+        }
+        Ok(expr)
+    }
+}
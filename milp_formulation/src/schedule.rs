@@ -0,0 +1,170 @@
+use crate::{solver::Solution, ExecutionTimeT, MilpFormulation, NameT};
+use mdsdf::vector::Vector;
+use std::collections::BTreeMap;
+
+/// Firing of an HSDF actor in a [`PeriodicSchedule`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActorSchedule<const N: usize> {
+    pub name: String,
+    pub execution_time: usize,
+    /// Start time of the first firing in every dimension. Firings of later iterations start
+    /// one period later each.
+    pub offset: Vector<N, f64>,
+}
+
+/// Firing of an HSDF actor in a given iteration, see [`PeriodicSchedule::unroll`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Firing<'a, const N: usize> {
+    pub actor: (usize, Vector<N, usize>),
+    pub name: &'a str,
+    pub iteration: usize,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Schedule in which every HSDF actor fires once per period, read from a solved
+/// [`MilpFormulation`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodicSchedule<const N: usize> {
+    /// Time between two iterations of the HSDF in every dimension. Every period covers
+    /// `iterations` iterations of the original graph, see [`PeriodicSchedule::throughput`].
+    pub period: Vector<N, f64>,
+    /// Iterations of the original graph the HSDF was unfolded to, see
+    /// [`MilpFormulation::iterations`].
+    pub iterations: usize,
+    pub actors: BTreeMap<(usize, Vector<N, usize>), ActorSchedule<N>>,
+}
+
+impl<const N: usize> PeriodicSchedule<N> {
+    /// Iterations of the original graph per time unit in every dimension.
+    pub fn throughput(&self) -> Vector<N, f64> {
+        self.period.map(|p| self.iterations as f64 / p)
+    }
+
+    /// Start time of `actor` in `iteration` of `dimension`.
+    pub fn start_time(
+        &self,
+        actor: (usize, Vector<N, usize>),
+        dimension: usize,
+        iteration: usize,
+    ) -> Option<f64> {
+        self.actors
+            .get(&actor)
+            .map(|a| a.offset[dimension] + iteration as f64 * self.period[dimension])
+    }
+
+    /// Firings of the first `iterations` iterations of `dimension`, sorted by start time.
+    pub fn unroll(&self, dimension: usize, iterations: usize) -> Vec<Firing<'_, N>> {
+        let mut firings = (0..iterations)
+            .flat_map(|iteration| {
+                self.actors.iter().map(move |(actor, a)| {
+                    let start = a.offset[dimension] + iteration as f64 * self.period[dimension];
+                    Firing {
+                        actor: *actor,
+                        name: &a.name,
+                        iteration,
+                        start,
+                        end: start + a.execution_time as f64,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        firings.sort_by(|a, b| a.start.total_cmp(&b.start));
+        firings
+    }
+}
+
+impl<'a, const N: usize, ExecutionTime: ExecutionTimeT<N>, Name: NameT<N>>
+    MilpFormulation<'a, N, ExecutionTime, Name>
+{
+    /// Reads the start times of every HSDF actor from `solution`, which has to come from
+    /// optimizing `self.model`. Returns `None` if the solution has no values or a throughput
+    /// is not positive.
+    pub fn schedule(&mut self, solution: &Solution) -> Option<PeriodicSchedule<N>> {
+        let mut throughput = Vector::<N, f64>::default();
+        for (d, t) in self.throughputs.iter().enumerate() {
+            throughput[d] = solution.value(*t).filter(|t| *t > 0.0)?;
+        }
+        let actors = self
+            .u
+            .iter()
+            .map(|(a, u)| {
+                let u = solution.value(*u)?;
+                Some((
+                    *a,
+                    ActorSchedule {
+                        name: (self.name)(*a),
+                        execution_time: (self.execution_time)(*a),
+                        offset: throughput.map(|t| u / t),
+                    },
+                ))
+            })
+            .collect::<Option<_>>()?;
+        Some(PeriodicSchedule {
+            period: throughput.map(|t| 1.0 / t),
+            iterations: self.iterations,
+            actors,
+        })
+    }
+}
+
+#[cfg(all(test, any(feature = "microlp", feature = "grb")))]
+mod tests {
+    use crate::{model::ModelSense, solver::DefaultSolver, MilpFormulation};
+    use mdsdf::{vector::Vector, Channel, HsdfChannel, Mdsdf};
+    use std::borrow::Cow;
+
+    #[test]
+    fn dependencies_hold() {
+        let mut sdf = Mdsdf::<1>::new(3);
+        for (source, target, production_rate, consumption_rate, initial_tokens) in [
+            (0, 1, 2, 3, 0),
+            (1, 2, 1, 2, 0),
+            (0, 0, 1, 1, 1),
+            (1, 1, 1, 1, 1),
+            (2, 2, 1, 1, 1),
+            (2, 0, 3, 1, 3),
+        ] {
+            sdf.add_channel(Channel {
+                production_rate: [production_rate].into(),
+                consumption_rate: [consumption_rate].into(),
+                source,
+                target,
+                initial_tokens: [initial_tokens].into(),
+            });
+        }
+        let hsdf = sdf.hsdf();
+        let execution_time = |(a, _): (usize, Vector<1, usize>)| [1, 2, 2][a];
+        let mut milp = MilpFormulation::new(Cow::Borrowed(&hsdf), execution_time, |(a, i)| {
+            format!("{}({})", ["a", "b", "c"][a], i[0])
+        })
+        .unwrap();
+        milp.model
+            .set_objective(milp.throughputs[0], ModelSense::Maximize)
+            .unwrap();
+        let solution = milp.model.optimize::<DefaultSolver>().unwrap();
+        let schedule = milp.schedule(&solution).unwrap();
+
+        assert_eq!(schedule.actors[&(1, [1].into())].name, "b(1)");
+        let period = schedule.period[0];
+        for HsdfChannel {
+            source,
+            target,
+            initial_tokens,
+            ..
+        } in hsdf.channels()
+        {
+            let start = schedule.start_time(source, 0, 0).unwrap();
+            let end = start + execution_time(source) as f64;
+            let ready = schedule
+                .start_time(target, 0, 0)
+                .map(|s| s + initial_tokens[0] as f64 * period)
+                .unwrap();
+            assert!(end <= ready + 1e-6);
+        }
+
+        let firings = schedule.unroll(0, 2);
+        assert_eq!(firings.len(), 12);
+        assert!(firings.windows(2).all(|f| f[0].start <= f[1].start));
+    }
+}
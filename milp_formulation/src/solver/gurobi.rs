@@ -0,0 +1,89 @@
+pub use ::grb;
+
+use super::{Solver, Status};
+use crate::model::{ModelSense, Sense, VarType};
+
+/// Gurobi backend, through the `grb` crate.
+///
+/// The underlying [`grb::Model`] is exposed so that parameters can be set before optimizing.
+pub struct Gurobi {
+    pub model: ::grb::Model,
+}
+
+fn expr(terms: &[(::grb::Var, f64)], constant: f64) -> ::grb::expr::LinExpr {
+    let mut expr = ::grb::expr::LinExpr::new();
+    for (v, c) in terms {
+        expr.add_term(*c, *v);
+    }
+    expr.add_constant(constant);
+    expr
+}
+
+impl Solver for Gurobi {
+    type Var = ::grb::Var;
+    type Error = ::grb::Error;
+
+    fn new(name: &str) -> ::grb::Result<Self> {
+        Ok(Self {
+            model: ::grb::Model::new(name)?,
+        })
+    }
+
+    fn add_var(
+        &mut self,
+        name: &str,
+        vtype: VarType,
+        lb: f64,
+        ub: f64,
+    ) -> ::grb::Result<Self::Var> {
+        let vtype = match vtype {
+            VarType::Continuous => ::grb::VarType::Continuous,
+            VarType::Integer => ::grb::VarType::Integer,
+        };
+        self.model.add_var(name, vtype, 0.0, lb, ub, [])
+    }
+
+    fn add_constr(
+        &mut self,
+        name: &str,
+        terms: &[(Self::Var, f64)],
+        sense: Sense,
+        rhs: f64,
+    ) -> ::grb::Result<()> {
+        let lhs = expr(terms, 0.0);
+        let constraint = match sense {
+            Sense::Less => ::grb::c!(lhs <= rhs),
+            Sense::Greater => ::grb::c!(lhs >= rhs),
+            Sense::Equal => ::grb::c!(lhs == rhs),
+        };
+        self.model.add_constr(name, constraint).map(|_| ())
+    }
+
+    fn set_objective(
+        &mut self,
+        terms: &[(Self::Var, f64)],
+        constant: f64,
+        sense: ModelSense,
+    ) -> ::grb::Result<()> {
+        let sense = match sense {
+            ModelSense::Minimize => ::grb::ModelSense::Minimize,
+            ModelSense::Maximize => ::grb::ModelSense::Maximize,
+        };
+        self.model.set_objective(expr(terms, constant), sense)
+    }
+
+    fn optimize(&mut self) -> ::grb::Result<Status> {
+        self.model.optimize()?;
+        Ok(match self.model.status()? {
+            ::grb::Status::Optimal => Status::Optimal,
+            ::grb::Status::Infeasible => Status::Infeasible,
+            ::grb::Status::Unbounded => Status::Unbounded,
+            ::grb::Status::InfOrUnbd => Status::InfeasibleOrUnbounded,
+            _ => Status::Interrupted,
+        })
+    }
+
+    fn value(&self, var: &Self::Var) -> ::grb::Result<f64> {
+        self.model.get_obj_attr(::grb::attr::X, var)
+    }
+}
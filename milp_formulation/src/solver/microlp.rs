@@ -0,0 +1,118 @@
+use super::{Solver, Status};
+use crate::model::{ModelSense, Sense, VarType};
+
+/// Terms, sense and right hand side of a constraint.
+type Row = (Vec<(usize, f64)>, Sense, f64);
+
+/// Pure Rust backend built on the `microlp` crate.
+///
+/// `microlp` takes the objective coefficients when variables are created, so the model is
+/// buffered and the problem is only built by `optimize`. Integer bounds are rounded inwards
+/// and saturate at the range of `i32`.
+#[derive(Debug, Clone, Default)]
+pub struct MicroLp {
+    vars: Vec<(VarType, f64, f64)>,
+    constrs: Vec<Row>,
+    objective: Vec<f64>,
+    sense: Option<ModelSense>,
+    values: Vec<f64>,
+}
+
+impl Solver for MicroLp {
+    type Var = usize;
+    type Error = ::microlp::Error;
+
+    fn new(_name: &str) -> Result<Self, Self::Error> {
+        Ok(Default::default())
+    }
+
+    fn add_var(
+        &mut self,
+        _name: &str,
+        vtype: VarType,
+        lb: f64,
+        ub: f64,
+    ) -> Result<usize, Self::Error> {
+        self.vars.push((vtype, lb, ub));
+        self.objective.push(0.0);
+        Ok(self.vars.len() - 1)
+    }
+
+    fn add_constr(
+        &mut self,
+        _name: &str,
+        terms: &[(usize, f64)],
+        sense: Sense,
+        rhs: f64,
+    ) -> Result<(), Self::Error> {
+        self.constrs.push((terms.to_vec(), sense, rhs));
+        Ok(())
+    }
+
+    fn set_objective(
+        &mut self,
+        terms: &[(usize, f64)],
+        _constant: f64,
+        sense: ModelSense,
+    ) -> Result<(), Self::Error> {
+        self.objective.iter_mut().for_each(|c| *c = 0.0);
+        for (v, c) in terms {
+            self.objective[*v] += c;
+        }
+        self.sense = Some(sense);
+        Ok(())
+    }
+
+    fn optimize(&mut self) -> Result<Status, Self::Error> {
+        use ::microlp::{ComparisonOp, OptimizationDirection, Problem};
+
+        let mut problem = Problem::new(match self.sense {
+            Some(ModelSense::Maximize) => OptimizationDirection::Maximize,
+            _ => OptimizationDirection::Minimize,
+        });
+        let vars = self
+            .vars
+            .iter()
+            .zip(self.objective.iter())
+            .map(|((vtype, lb, ub), c)| match vtype {
+                VarType::Continuous => problem.add_var(*c, (*lb, *ub)),
+                VarType::Integer => {
+                    problem.add_integer_var(*c, (lb.ceil() as i32, ub.floor() as i32))
+                }
+            })
+            .collect::<Vec<_>>();
+        for (terms, sense, rhs) in self.constrs.iter() {
+            let op = match sense {
+                Sense::Less => ComparisonOp::Le,
+                Sense::Greater => ComparisonOp::Ge,
+                Sense::Equal => ComparisonOp::Eq,
+            };
+            problem.add_constraint(
+                terms
+                    .iter()
+                    .map(|(v, c)| (vars[*v], *c))
+                    .collect::<Vec<_>>(),
+                op,
+                *rhs,
+            );
+        }
+
+        self.values.clear();
+        match problem.solve() {
+            Ok(solution) => {
+                self.values = vars.iter().map(|v| *solution.var_value(*v)).collect();
+                Ok(Status::Optimal)
+            }
+            Err(::microlp::Error::Infeasible) => Ok(Status::Infeasible),
+            Err(::microlp::Error::Unbounded) => Ok(Status::Unbounded),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn value(&self, var: &usize) -> Result<f64, Self::Error> {
+        self.values
+            .get(*var)
+            .copied()
+            .ok_or_else(|| ::microlp::Error::InternalError(format!("no value for variable {var}")))
+    }
+}
@@ -0,0 +1,112 @@
+#[cfg(feature = "grb")]
+pub mod gurobi;
+#[cfg(feature = "microlp")]
+pub mod microlp;
+
+use crate::model::{Constraint, Expr, ModelSense, Sense, Var, VarType};
+
+/// Solver used when none is given explicitly: Gurobi if the `grb` feature is enabled, the pure
+/// Rust `microlp` otherwise.
+#[cfg(feature = "grb")]
+pub type DefaultSolver = gurobi::Gurobi;
+#[cfg(all(feature = "microlp", not(feature = "grb")))]
+pub type DefaultSolver = microlp::MicroLp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Optimal,
+    Infeasible,
+    Unbounded,
+    InfeasibleOrUnbounded,
+    /// The solver stopped before proving optimality, e.g. on a time limit.
+    Interrupted,
+}
+
+/// Backend that optimizes a [`crate::model::Model`].
+///
+/// The model is handed over variable by variable and constraint by constraint, with the
+/// terms of every expression merged, before `optimize` is called once.
+pub trait Solver: Sized {
+    type Var: Clone;
+    type Error: std::error::Error;
+
+    fn new(name: &str) -> Result<Self, Self::Error>;
+
+    fn add_var(
+        &mut self,
+        name: &str,
+        vtype: VarType,
+        lb: f64,
+        ub: f64,
+    ) -> Result<Self::Var, Self::Error>;
+
+    fn add_constr(
+        &mut self,
+        name: &str,
+        terms: &[(Self::Var, f64)],
+        sense: Sense,
+        rhs: f64,
+    ) -> Result<(), Self::Error>;
+
+    fn set_objective(
+        &mut self,
+        terms: &[(Self::Var, f64)],
+        constant: f64,
+        sense: ModelSense,
+    ) -> Result<(), Self::Error>;
+
+    fn optimize(&mut self) -> Result<Status, Self::Error>;
+
+    /// Value of `var` in the optimal solution found by the last call to `optimize`.
+    fn value(&self, var: &Self::Var) -> Result<f64, Self::Error>;
+}
+
+/// Outcome of optimizing a [`crate::model::Model`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub status: Status,
+    values: Vec<f64>,
+    objective: Option<f64>,
+}
+
+impl Solution {
+    pub(crate) fn new(status: Status, values: Vec<f64>, objective: &Expr) -> Self {
+        let objective = (status == Status::Optimal).then(|| {
+            objective
+                .terms()
+                .iter()
+                .map(|(Var(v), c)| values[*v] * c)
+                .sum::<f64>()
+                + objective.constant()
+        });
+        Self {
+            status,
+            values,
+            objective,
+        }
+    }
+
+    /// Value of `var`, if an optimal solution was found.
+    pub fn value(&self, Var(v): Var) -> Option<f64> {
+        self.values.get(v).copied()
+    }
+
+    pub fn objective(&self) -> Option<f64> {
+        self.objective
+    }
+
+    /// Amount by which `constraint` is satisfied, negative if it is violated.
+    pub fn slack(&self, constraint: &Constraint) -> Option<f64> {
+        let lhs = constraint
+            .expr
+            .terms()
+            .iter()
+            .map(|(v, c)| self.value(*v).map(|v| v * c))
+            .sum::<Option<f64>>()?;
+        Some(match constraint.sense {
+            Sense::Less => constraint.rhs - lhs,
+            Sense::Greater => lhs - constraint.rhs,
+            Sense::Equal => -(lhs - constraint.rhs).abs(),
+        })
+    }
+}
@@ -0,0 +1,322 @@
+use crate::{ParseError, Sdf3Graph};
+use mdsdf::vector::Vector;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RawArchitecture {
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "$value", default)]
+    elements: Vec<ArchitectureElement>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum ArchitectureElement {
+    Tile(RawTile),
+    Connection(RawConnection),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RawTile {
+    name: String,
+    #[serde(rename = "$value", default)]
+    elements: Vec<TileElement>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum TileElement {
+    Processor {
+        name: String,
+        #[serde(rename = "type")]
+        t: String,
+    },
+    Memory {
+        name: String,
+        size: usize,
+    },
+    NetworkInterface {},
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawConnection {
+    name: String,
+    src_tile: String,
+    dst_tile: String,
+    #[serde(default)]
+    delay: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RawMapping {
+    #[serde(rename = "$value", default)]
+    elements: Vec<MappingElement>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum MappingElement {
+    Tile(RawTileMapping),
+    Connection(RawMapped),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RawTileMapping {
+    name: String,
+    #[serde(rename = "$value", default)]
+    elements: Vec<TileMappingElement>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum TileMappingElement {
+    Processor(RawMapped),
+    Memory(RawMapped),
+    NetworkInterface(RawMapped),
+}
+
+/// Element of the architecture and the actors and channels mapped onto it.
+#[derive(Debug, Serialize, Deserialize)]
+struct RawMapped {
+    name: String,
+    #[serde(rename = "$value", default)]
+    elements: Vec<MappedElement>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum MappedElement {
+    Actor { name: String },
+    Channel { name: String },
+    Schedule {},
+}
+
+/// Platform of an SDF3 file, from its `architectureGraph`. Processors and memories of all tiles
+/// are numbered together, in the order of the file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Architecture {
+    pub name: String,
+    pub tiles: Vec<String>,
+    pub processors: Vec<Processor>,
+    pub memories: Vec<Memory>,
+    pub connections: Vec<Connection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Processor {
+    pub name: String,
+    /// Index into [`Architecture::tiles`].
+    pub tile: usize,
+    /// Matched against [`crate::ProcessorType::name`] for the execution times.
+    pub processor_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Memory {
+    pub name: String,
+    pub tile: usize,
+    pub size: usize,
+}
+
+/// Link between two tiles of an [`Architecture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection {
+    pub name: String,
+    pub source: usize,
+    pub target: usize,
+    pub delay: usize,
+}
+
+/// Binding of the actors and channels of an [`Sdf3Graph`] to its [`Architecture`], from the
+/// `mapping` section. Entries are indexed like [`Sdf3Graph::actors`] and
+/// [`Sdf3Graph::channels`] and refer to [`Architecture::processors`],
+/// [`Architecture::memories`] and [`Architecture::connections`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Mapping {
+    pub processors: Vec<Option<usize>>,
+    /// Memory holding the state of every actor.
+    pub states: Vec<Option<usize>>,
+    /// Memories holding the buffer of every channel, one for each tile it spans.
+    pub buffers: Vec<Vec<usize>>,
+    pub connections: Vec<Option<usize>>,
+}
+
+impl Architecture {
+    /// Size of every memory, in the order the Python `CyclicScheduler.add_memory` of
+    /// `cyclic_scheduler` should get them.
+    pub fn memory_sizes(&self) -> Vec<usize> {
+        self.memories.iter().map(|m| m.size).collect()
+    }
+}
+
+impl Mapping {
+    /// Processor of every HSDF actor, as `cyclic_scheduler::cyclic_scheduler` expects it, or
+    /// `None` if an actor is not mapped.
+    pub fn processor_assignment<const N: usize>(
+        &self,
+    ) -> Option<impl Fn((usize, Vector<N, usize>)) -> usize> {
+        let processors = self
+            .processors
+            .iter()
+            .copied()
+            .collect::<Option<Vec<_>>>()?;
+        Some(move |(a, _): (usize, Vector<N, usize>)| processors[a])
+    }
+}
+
+pub(crate) fn architecture(raw: RawArchitecture) -> Result<Architecture, ParseError> {
+    let mut architecture = Architecture {
+        name: raw.name,
+        ..Default::default()
+    };
+    let mut tiles = BTreeMap::new();
+    let mut connections = Vec::new();
+    for element in raw.elements {
+        match element {
+            ArchitectureElement::Tile(RawTile { name, elements }) => {
+                let tile = architecture.tiles.len();
+                if tiles.insert(name.clone(), tile).is_some() {
+                    return Err(ParseError::DuplicateTile(name));
+                }
+                for element in elements {
+                    match element {
+                        TileElement::Processor { name, t } => {
+                            architecture.processors.push(Processor {
+                                name,
+                                tile,
+                                processor_type: t,
+                            })
+                        }
+                        TileElement::Memory { name, size } => {
+                            architecture.memories.push(Memory { name, tile, size })
+                        }
+                        TileElement::NetworkInterface {} => {}
+                    }
+                }
+                architecture.tiles.push(name);
+            }
+            ArchitectureElement::Connection(c) => connections.push(c),
+        }
+    }
+
+    let tile = |name: &str| {
+        tiles
+            .get(name)
+            .copied()
+            .ok_or_else(|| ParseError::UnknownTile(name.to_string()))
+    };
+    architecture.connections = connections
+        .into_iter()
+        .map(|c| {
+            Ok(Connection {
+                source: tile(&c.src_tile)?,
+                target: tile(&c.dst_tile)?,
+                name: c.name,
+                delay: c.delay,
+            })
+        })
+        .collect::<Result<_, ParseError>>()?;
+    Ok(architecture)
+}
+
+pub(crate) fn mapping(
+    raw: RawMapping,
+    graph: &Sdf3Graph,
+    architecture: &Architecture,
+) -> Result<Mapping, ParseError> {
+    let mut mapping = Mapping {
+        processors: vec![None; graph.actors.len()],
+        states: vec![None; graph.actors.len()],
+        buffers: vec![Vec::new(); graph.channels.len()],
+        connections: vec![None; graph.channels.len()],
+    };
+    let actor = |name: &str| {
+        graph
+            .actors
+            .iter()
+            .position(|a| a.name == name)
+            .ok_or_else(|| ParseError::UnknownActor(name.to_string()))
+    };
+    let channel = |name: &str| {
+        graph
+            .channels
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| ParseError::UnknownChannel(name.to_string()))
+    };
+
+    for element in raw.elements {
+        match element {
+            MappingElement::Tile(RawTileMapping { name, elements }) => {
+                let tile = architecture
+                    .tiles
+                    .iter()
+                    .position(|t| *t == name)
+                    .ok_or_else(|| ParseError::UnknownTile(name.clone()))?;
+                for element in elements {
+                    match element {
+                        TileMappingElement::Processor(RawMapped {
+                            name: processor,
+                            elements,
+                        }) => {
+                            let p = architecture
+                                .processors
+                                .iter()
+                                .position(|p| p.tile == tile && p.name == processor)
+                                .ok_or_else(|| ParseError::UnknownProcessor {
+                                    tile: name.clone(),
+                                    processor,
+                                })?;
+                            for element in elements {
+                                if let MappedElement::Actor { name } = element {
+                                    mapping.processors[actor(&name)?] = Some(p);
+                                }
+                            }
+                        }
+                        TileMappingElement::Memory(RawMapped {
+                            name: memory,
+                            elements,
+                        }) => {
+                            let m = architecture
+                                .memories
+                                .iter()
+                                .position(|m| m.tile == tile && m.name == memory)
+                                .ok_or_else(|| ParseError::UnknownMemory {
+                                    tile: name.clone(),
+                                    memory,
+                                })?;
+                            for element in elements {
+                                match element {
+                                    MappedElement::Actor { name } => {
+                                        mapping.states[actor(&name)?] = Some(m)
+                                    }
+                                    MappedElement::Channel { name } => {
+                                        mapping.buffers[channel(&name)?].push(m)
+                                    }
+                                    MappedElement::Schedule {} => {}
+                                }
+                            }
+                        }
+                        TileMappingElement::NetworkInterface(_) => {}
+                    }
+                }
+            }
+            MappingElement::Connection(RawMapped { name, elements }) => {
+                let c = architecture
+                    .connections
+                    .iter()
+                    .position(|c| c.name == name)
+                    .ok_or(ParseError::UnknownConnection(name))?;
+                for element in elements {
+                    if let MappedElement::Channel { name } = element {
+                        mapping.connections[channel(&name)?] = Some(c);
+                    }
+                }
+            }
+        }
+    }
+    Ok(mapping)
+}
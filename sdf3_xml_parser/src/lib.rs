@@ -1,14 +1,19 @@
-#![feature(iterator_try_collect)]
+pub mod architecture;
+mod write;
 
 use std::collections::BTreeMap;
+use std::fmt;
 
-use buffer_sizing::BufferedMrsdf;
+use architecture::{Architecture, Mapping, RawArchitecture, RawMapping};
+use mdsdf::{ChannelIndex, Mdsdf};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct Sdf3 {
     application_graph: ApplicationGraph,
+    architecture_graph: Option<RawArchitecture>,
+    mapping: Option<RawMapping>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,20 +25,22 @@ struct ApplicationGraph {
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Sdf {
+    #[serde(default)]
+    name: String,
     #[serde(rename = "$value")]
     actors_or_channel: Vec<ActorOrChannel>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Actor {
+struct RawActor {
     name: String,
-    #[serde(rename = "$value")]
-    ports: Vec<Port>,
+    #[serde(rename = "$value", default)]
+    ports: Vec<RawPort>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct Port {
+struct RawPort {
     name: String,
     #[serde(rename = "type")]
     t: String,
@@ -42,39 +49,41 @@ struct Port {
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct Channel {
+struct RawChannel {
     name: String,
     src_actor: String,
     src_port: String,
     dst_actor: String,
     dst_port: String,
-    initial_tokens: Option<isize>,
+    initial_tokens: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct SdfProperties {
-    #[serde(rename = "$value")]
-    properties: Vec<ActorOrChannelProperties>,
+    #[serde(rename = "$value", default)]
+    properties: Vec<Properties>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 enum ActorOrChannel {
-    Actor(Actor),
-    Channel(Channel),
+    Actor(RawActor),
+    Channel(RawChannel),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-enum ActorOrChannelProperties {
-    ActorProperties(ActorProperties),
-    ChannelProperties(ChannelProperties),
-    GraphProperties,
+enum Properties {
+    #[serde(rename = "actorProperties")]
+    Actor(ActorProperties),
+    #[serde(rename = "channelProperties")]
+    Channel(RawChannelProperties),
+    #[serde(rename = "graphProperties")]
+    Graph(GraphProperties),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ActorProperties {
-    #[serde(rename = "$value")]
+    #[serde(rename = "$value", default)]
     processors: Vec<Processor>,
     actor: String,
 }
@@ -83,145 +92,703 @@ struct ActorProperties {
 struct Processor {
     #[serde(rename = "type")]
     t: String,
+    #[serde(default)]
     default: bool,
-    #[serde(rename = "$value")]
-    execution_times: Vec<ExecutionTime>,
+    #[serde(rename = "$value", default)]
+    properties: Vec<ProcessorProperty>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum ProcessorProperty {
+    ExecutionTime { time: usize },
+    Memory(Memory),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Memory {
+    state_size: Option<StateSize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct ExecutionTime {
-    time: usize,
+struct StateSize {
+    max: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct ChannelProperties {}
+struct RawChannelProperties {
+    channel: String,
+    #[serde(rename = "$value", default)]
+    properties: Vec<ChannelProperty>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum ChannelProperty {
+    BufferSize(BufferSize),
+    TokenSize { sz: usize },
+    Bandwidth {},
+    Latency {},
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphProperties {
+    time_constraints: Option<TimeConstraints>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TimeConstraints {
+    throughput: Option<f64>,
+}
+
+/// Application graph of an SDF3 file, with the platform and mapping if it has them, see
+/// [`parse`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Sdf3Graph {
+    pub name: String,
+    pub actors: Vec<Actor>,
+    pub channels: Vec<Channel>,
+    /// Minimum number of graph iterations per time unit from `timeConstraints`.
+    pub throughput: Option<f64>,
+    pub architecture: Option<Architecture>,
+    /// Only present together with `architecture`.
+    pub mapping: Option<Mapping>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Actor {
+    pub name: String,
+    pub ports: Vec<Port>,
+    /// Processor types the actor can run on, from its `actorProperties`.
+    pub processors: Vec<ProcessorType>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortDirection {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Port {
+    pub name: String,
+    pub direction: PortDirection,
+    pub rate: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessorType {
+    pub name: String,
+    pub default: bool,
+    pub execution_time: usize,
+    /// Memory the actor needs for its state on this processor type.
+    pub state_size: Option<usize>,
+}
+
+/// Channel between two actors of an [`Sdf3Graph`]. Actors and ports are indices into
+/// [`Sdf3Graph::actors`] and [`Actor::ports`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Channel {
+    pub name: String,
+    pub source: usize,
+    pub source_port: usize,
+    pub target: usize,
+    pub target_port: usize,
+    /// `None` if the file leaves out the `initialTokens` attribute.
+    pub initial_tokens: Option<usize>,
+    pub properties: ChannelProperties,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChannelProperties {
+    /// Size of a token, in the same unit as [`ProcessorType::state_size`].
+    pub token_size: Option<usize>,
+    pub buffer_size: Option<BufferSize>,
+}
+
+/// Capacity of the buffer of a channel, in tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BufferSize {
+    /// Capacity when producer and consumer share a memory.
+    #[serde(rename = "sz")]
+    pub size: Option<usize>,
+    /// Capacities of the parts in the memory of the producer, the consumer and the
+    /// connection between them, when the channel is mapped onto two tiles.
+    #[serde(rename = "src")]
+    pub source: Option<usize>,
+    #[serde(rename = "dst")]
+    pub target: Option<usize>,
+    #[serde(rename = "mem")]
+    pub memory: Option<usize>,
+}
+
+/// Reason why [`parse`] rejected a file.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The file is not well-formed XML or does not have the structure of an SDF3 file.
+    Xml(serde_xml_rs::Error),
+    DuplicateActor(String),
+    DuplicateChannel(String),
+    DuplicateTile(String),
+    UnknownActor(String),
+    UnknownChannel(String),
+    UnknownTile(String),
+    UnknownProcessor {
+        tile: String,
+        processor: String,
+    },
+    UnknownMemory {
+        tile: String,
+        memory: String,
+    },
+    UnknownConnection(String),
+    /// The file has a `mapping` but no `architectureGraph`.
+    MissingArchitecture,
+    UnknownPort {
+        actor: String,
+        port: String,
+    },
+    /// A port has a `type` other than `in` or `out`.
+    PortType {
+        actor: String,
+        port: String,
+        t: String,
+    },
+    /// `channel` leaves from an input port or enters an output port.
+    PortDirection {
+        channel: String,
+        port: String,
+    },
+    MissingExecutionTime {
+        actor: String,
+        processor: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Xml(e) => write!(f, "invalid SDF3 file: {e}"),
+            Self::DuplicateActor(actor) => write!(f, "actor {actor} is defined twice"),
+            Self::DuplicateChannel(channel) => write!(f, "channel {channel} is defined twice"),
+            Self::DuplicateTile(tile) => write!(f, "tile {tile} is defined twice"),
+            Self::UnknownActor(actor) => write!(f, "actor {actor} is not defined"),
+            Self::UnknownChannel(channel) => write!(f, "channel {channel} is not defined"),
+            Self::UnknownTile(tile) => write!(f, "tile {tile} is not defined"),
+            Self::UnknownProcessor { tile, processor } => {
+                write!(f, "tile {tile} has no processor {processor}")
+            }
+            Self::UnknownMemory { tile, memory } => {
+                write!(f, "tile {tile} has no memory {memory}")
+            }
+            Self::UnknownConnection(connection) => {
+                write!(f, "connection {connection} is not defined")
+            }
+            Self::MissingArchitecture => write!(f, "mapping without an architecture graph"),
+            Self::UnknownPort { actor, port } => {
+                write!(f, "actor {actor} has no port {port}")
+            }
+            Self::PortType { actor, port, t } => write!(
+                f,
+                "port {port} of actor {actor} has type {t} instead of in or out"
+            ),
+            Self::PortDirection { channel, port } => write!(
+                f,
+                "channel {channel} is connected to port {port} in the wrong direction"
+            ),
+            Self::MissingExecutionTime { actor, processor } => write!(
+                f,
+                "actor {actor} has no execution time on processor {processor}"
+            ),
+        }
+    }
+}
+
+/// Reason why [`Sdf3Graph::from_mdsdf`] could not convert a graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromMdsdfError {
+    /// `names` or `execution_times` does not have one entry per actor.
+    ActorCount {
+        actors: usize,
+        names: usize,
+        execution_times: usize,
+    },
+    /// SDF3 has no negative initial tokens.
+    NegativeTokens {
+        channel: ChannelIndex,
+        initial_tokens: isize,
+    },
+}
+
+impl fmt::Display for FromMdsdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ActorCount {
+                actors,
+                names,
+                execution_times,
+            } => write!(
+                f,
+                "the graph has {actors} actors but {names} names and {execution_times} execution times"
+            ),
+            Self::NegativeTokens {
+                channel,
+                initial_tokens,
+            } => write!(f, "channel {channel} holds {initial_tokens} initial tokens"),
+        }
+    }
+}
+
+impl std::error::Error for FromMdsdfError {}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Xml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_xml_rs::Error> for ParseError {
+    fn from(e: serde_xml_rs::Error) -> Self {
+        Self::Xml(e)
+    }
+}
+
+impl Actor {
+    /// Processor type marked as default, or the first one if none is.
+    pub fn default_processor(&self) -> Option<&ProcessorType> {
+        self.processors
+            .iter()
+            .find(|p| p.default)
+            .or(self.processors.first())
+    }
+
+    pub fn execution_time(&self, processor: &str) -> Option<usize> {
+        self.processors
+            .iter()
+            .find(|p| p.name == processor)
+            .map(|p| p.execution_time)
+    }
+}
 
-pub fn parse(
-    s: &str,
-) -> (
-    milp_formulation::MilpFormulation<
-        'static,
-        1,
-        impl milp_formulation::ExecutionTimeT<1>,
-        impl milp_formulation::NameT<1>,
-    >,
-    BTreeMap<usize, usize>,
-    Vec<grb::Var>,
-) {
-    use serde_xml_rs::from_str;
-    use std::borrow::Cow;
-    use std::collections::BTreeMap;
+impl Sdf3Graph {
+    /// Single-dimensional graph with the same actors and channels, in the same order.
+    /// Channels without initial tokens hold none.
+    pub fn mdsdf(&self) -> (Mdsdf<1>, Vec<ChannelIndex>) {
+        let mut sdf = Mdsdf::new(self.actors.len());
+        let channels = self
+            .channels
+            .iter()
+            .map(|c| {
+                sdf.add_channel(mdsdf::Channel {
+                    production_rate: [self.actors[c.source].ports[c.source_port].rate].into(),
+                    consumption_rate: [self.actors[c.target].ports[c.target_port].rate].into(),
+                    source: c.source,
+                    target: c.target,
+                    initial_tokens: [c.initial_tokens.unwrap_or(0) as isize].into(),
+                })
+            })
+            .collect();
+        (sdf, channels)
+    }
+}
 
-    let sdf: Sdf3 = from_str(s).unwrap();
+pub fn parse(s: &str) -> Result<Sdf3Graph, ParseError> {
+    let sdf3: Sdf3 = serde_xml_rs::from_str(s)?;
     let ApplicationGraph {
         sdf,
         sdf_properties,
-    } = &sdf.application_graph;
-    let actors = sdf
-        .actors_or_channel
-        .iter()
-        .filter_map(|e| {
-            if let ActorOrChannel::Actor(a) = e {
-                Some(a)
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<&Actor>>();
-    let channels = sdf
-        .actors_or_channel
-        .iter()
-        .filter_map(|e| {
-            if let ActorOrChannel::Channel(c) = e {
-                Some(c)
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<&Channel>>();
-    let ports: BTreeMap<(&str, &str), &Port> = actors
-        .iter()
-        .flat_map(|a| a.ports.iter().map(|p| ((a.name.as_str(), p.name.as_str()), p)))
-        .collect();
-    let actor_indicies: BTreeMap<&str, usize> = actors
-        .iter()
+    } = sdf3.application_graph;
+
+    let mut actors = Vec::new();
+    let mut raw_channels = Vec::new();
+    for e in sdf.actors_or_channel {
+        match e {
+            ActorOrChannel::Actor(a) => actors.push(a),
+            ActorOrChannel::Channel(c) => raw_channels.push(c),
+        }
+    }
+
+    let mut actor_indices = BTreeMap::new();
+    let mut actors = actors
+        .into_iter()
         .enumerate()
-        .map(|(i, a)| (a.name.as_str(), i))
-        .collect();
-    let names = actor_indicies.iter().map(|(n, i)| (*i, n.to_string())).collect::<BTreeMap<_, _>>();
-    let mut result = mdsdf::Mdsdf::<1>::new(actors.len());
-
-    let execution_times: BTreeMap<usize, usize> = sdf_properties
-        .properties
-        .iter()
-        .filter_map(|e| {
-            if let ActorOrChannelProperties::ActorProperties(a) = e {
-                Some(a)
-            } else {
-                None
+        .map(|(i, RawActor { name, ports })| {
+            if actor_indices.insert(name.clone(), i).is_some() {
+                return Err(ParseError::DuplicateActor(name));
             }
+            let ports = ports
+                .into_iter()
+                .map(
+                    |RawPort {
+                         name: port,
+                         t,
+                         rate,
+                     }| {
+                        let direction = match t.as_str() {
+                            "in" => PortDirection::In,
+                            "out" => PortDirection::Out,
+                            _ => {
+                                return Err(ParseError::PortType {
+                                    actor: name.clone(),
+                                    port,
+                                    t,
+                                })
+                            }
+                        };
+                        Ok(Port {
+                            name: port,
+                            direction,
+                            rate,
+                        })
+                    },
+                )
+                .collect::<Result<_, _>>()?;
+            Ok(Actor {
+                name,
+                ports,
+                processors: Vec::new(),
+            })
         })
-        .map(|p| {
-            (
-                *actor_indicies.get(p.actor.as_str()).unwrap(),
-                p.processors
-                    .iter()
-                    .filter(|e| e.default)
-                    .nth(0)
-                    .unwrap()
-                    .execution_times[0]
-                    .time,
-            )
-        })
-        .collect();
-    
-    let mut channels_to_buffer = Vec::new();
-    for Channel {
-        name,
-        src_actor,
-        src_port,
-        dst_actor,
-        dst_port,
-        initial_tokens,
-    } in channels.iter()
-    {
-        if let Some(initial_tokens) = initial_tokens {
-            result.add_channel(mdsdf::Channel {
-                production_rate: [ports.get(&(src_actor.as_str(), src_port.as_str())).unwrap().rate].into(),
-                consumption_rate: [ports.get(&(dst_actor.as_str(), dst_port.as_str())).unwrap().rate].into(),
-                source: *actor_indicies.get(src_actor.as_str()).unwrap(),
-                target: *actor_indicies.get(dst_actor.as_str()).unwrap(),
-                initial_tokens: [*initial_tokens].into(),
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let endpoint = |channel: &str, actor: &str, port: &str, direction: PortDirection| {
+        let a = *actor_indices
+            .get(actor)
+            .ok_or_else(|| ParseError::UnknownActor(actor.to_string()))?;
+        let p = actors[a]
+            .ports
+            .iter()
+            .position(|p| p.name == port)
+            .ok_or_else(|| ParseError::UnknownPort {
+                actor: actor.to_string(),
+                port: port.to_string(),
+            })?;
+        if actors[a].ports[p].direction != direction {
+            return Err(ParseError::PortDirection {
+                channel: channel.to_string(),
+                port: port.to_string(),
             });
-        } else {
-            let channel = mdsdf::Channel {
-                production_rate: [ports.get(&(src_actor.as_str(), src_port.as_str())).unwrap().rate].into(),
-                consumption_rate: [ports.get(&(dst_actor.as_str(), dst_port.as_str())).unwrap().rate].into(),
-                source: *actor_indicies.get(src_actor.as_str()).unwrap(),
-                target: *actor_indicies.get(dst_actor.as_str()).unwrap(),
-                initial_tokens: [0isize].into(),
-            };
-            channels_to_buffer.push((result.add_channel(channel), name));
+        }
+        Ok((a, p))
+    };
+    let mut channels = raw_channels
+        .into_iter()
+        .map(|c| {
+            let (source, source_port) =
+                endpoint(&c.name, &c.src_actor, &c.src_port, PortDirection::Out)?;
+            let (target, target_port) =
+                endpoint(&c.name, &c.dst_actor, &c.dst_port, PortDirection::In)?;
+            Ok(Channel {
+                name: c.name,
+                source,
+                source_port,
+                target,
+                target_port,
+                initial_tokens: c.initial_tokens,
+                properties: ChannelProperties::default(),
+            })
+        })
+        .collect::<Result<Vec<_>, ParseError>>()?;
+
+    let mut channel_indices = BTreeMap::new();
+    for (i, c) in channels.iter().enumerate() {
+        if channel_indices.insert(c.name.clone(), i).is_some() {
+            return Err(ParseError::DuplicateChannel(c.name.clone()));
+        }
+    }
+    let mut throughput = None;
+    for properties in sdf_properties.properties {
+        match properties {
+            Properties::Actor(ActorProperties { processors, actor }) => {
+                let a = *actor_indices
+                    .get(&actor)
+                    .ok_or_else(|| ParseError::UnknownActor(actor.clone()))?;
+                for Processor {
+                    t,
+                    default,
+                    properties,
+                } in processors
+                {
+                    let mut execution_time = None;
+                    let mut state_size = None;
+                    for property in properties {
+                        match property {
+                            ProcessorProperty::ExecutionTime { time } => {
+                                execution_time.get_or_insert(time);
+                            }
+                            ProcessorProperty::Memory(Memory {
+                                state_size: Some(StateSize { max }),
+                            }) => state_size = Some(max),
+                            ProcessorProperty::Memory(Memory { state_size: None }) => {}
+                        }
+                    }
+                    let execution_time =
+                        execution_time.ok_or_else(|| ParseError::MissingExecutionTime {
+                            actor: actor.clone(),
+                            processor: t.clone(),
+                        })?;
+                    actors[a].processors.push(ProcessorType {
+                        name: t,
+                        default,
+                        execution_time,
+                        state_size,
+                    });
+                }
+            }
+            Properties::Channel(RawChannelProperties {
+                channel,
+                properties,
+            }) => {
+                let c = *channel_indices
+                    .get(&channel)
+                    .ok_or(ParseError::UnknownChannel(channel))?;
+                let channel = &mut channels[c].properties;
+                for property in properties {
+                    match property {
+                        ChannelProperty::BufferSize(buffer_size) => {
+                            channel.buffer_size = Some(buffer_size)
+                        }
+                        ChannelProperty::TokenSize { sz } => channel.token_size = Some(sz),
+                        ChannelProperty::Bandwidth {} | ChannelProperty::Latency {} => {}
+                    }
+                }
+            }
+            Properties::Graph(GraphProperties { time_constraints }) => {
+                throughput = time_constraints.and_then(|t| t.throughput);
+            }
         }
     }
 
-    let mut milp = milp_formulation::MilpFormulation::new(
-        Cow::Owned(result.clone().into_hsdf().into()),
-        {
-            let execution_times = execution_times.clone();
-            move |(i, _)| *execution_times.get(&i).unwrap()
-        },
-        move |(i, j)| format!("{}({})", names.get(&i).unwrap(), j[0]),
-    )
-    .unwrap();
-
-    let model = &mut milp.model;
-    let buffers = channels_to_buffer.iter().map(|(_, name)| grb::add_ctsvar!(model, name: name, bounds: 0..)).try_collect::<Vec<_>>().unwrap();
-
-    let mut buffer_sizing = BufferedMrsdf::new(&mut milp);
-    for ((channel, _), buffer_size) in channels_to_buffer.iter().zip(buffers.iter()) {
-        buffer_sizing.add_buffer(*channel, [buffer_size.into()].into()).unwrap();
+    let mut graph = Sdf3Graph {
+        name: sdf.name,
+        actors,
+        channels,
+        throughput,
+        architecture: sdf3
+            .architecture_graph
+            .map(architecture::architecture)
+            .transpose()?,
+        mapping: None,
+    };
+    if let Some(mapping) = sdf3.mapping {
+        let architecture = graph
+            .architecture
+            .as_ref()
+            .ok_or(ParseError::MissingArchitecture)?;
+        graph.mapping = Some(architecture::mapping(mapping, &graph, architecture)?);
     }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) const EXAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sdf3 type="sdf" version="1.0">
+  <applicationGraph name="example">
+    <sdf name="example" type="Example">
+      <actor name="a" type="A">
+        <port name="out" type="out" rate="2"/>
+        <port name="self_in" type="in" rate="1"/>
+        <port name="self_out" type="out" rate="1"/>
+      </actor>
+      <actor name="b" type="B">
+        <port name="in" type="in" rate="3"/>
+      </actor>
+      <channel name="ab" srcActor="a" srcPort="out" dstActor="b" dstPort="in"/>
+      <channel name="aa" srcActor="a" srcPort="self_out" dstActor="a" dstPort="self_in" initialTokens="1"/>
+    </sdf>
+    <sdfProperties>
+      <actorProperties actor="a">
+        <processor type="p1" default="true">
+          <executionTime time="2"/>
+          <memory>
+            <stateSize max="16"/>
+          </memory>
+        </processor>
+        <processor type="p2">
+          <executionTime time="5"/>
+        </processor>
+      </actorProperties>
+      <actorProperties actor="b">
+        <processor type="p1" default="true">
+          <executionTime time="1"/>
+        </processor>
+      </actorProperties>
+      <channelProperties channel="ab">
+        <bufferSize sz="6" src="4" dst="3" mem="0"/>
+        <tokenSize sz="8"/>
+      </channelProperties>
+      <channelProperties channel="aa"/>
+      <graphProperties>
+        <timeConstraints>
+          <throughput>0.25</throughput>
+        </timeConstraints>
+      </graphProperties>
+    </sdfProperties>
+  </applicationGraph>
+</sdf3>"#;
+
+    #[test]
+    fn parse_example() {
+        let graph = parse(EXAMPLE).unwrap();
+        assert_eq!(graph.name, "example");
+        assert_eq!(graph.actors.len(), 2);
+        assert_eq!(graph.actors[0].execution_time("p2"), Some(5));
+        assert_eq!(
+            graph.actors[0].default_processor().unwrap().execution_time,
+            2
+        );
+        assert_eq!(graph.actors[0].processors[0].state_size, Some(16));
+        assert_eq!(graph.actors[0].processors[1].state_size, None);
+        assert_eq!(graph.channels[0].initial_tokens, None);
+        assert_eq!(
+            graph.channels[0].properties,
+            ChannelProperties {
+                token_size: Some(8),
+                buffer_size: Some(BufferSize {
+                    size: Some(6),
+                    source: Some(4),
+                    target: Some(3),
+                    memory: Some(0),
+                }),
+            }
+        );
+        assert_eq!(graph.channels[1].properties, ChannelProperties::default());
+        assert_eq!(graph.throughput, Some(0.25));
+        assert_eq!(
+            (graph.channels[1].source, graph.channels[1].target_port),
+            (0, 1)
+        );
 
-    (milp, execution_times, buffers)
+        let (sdf, channels) = graph.mdsdf();
+        let ab = sdf.get_channel(channels[0]);
+        assert_eq!((ab.production_rate[0], ab.consumption_rate[0]), (2, 3));
+        assert_eq!(*sdf.hsdf().repetition_vector, [[3].into(), [2].into()]);
+    }
+
+    #[test]
+    fn errors() {
+        let unknown = EXAMPLE.replace(r#"dstActor="b""#, r#"dstActor="c""#);
+        assert!(matches!(parse(&unknown), Err(ParseError::UnknownActor(a)) if a == "c"));
+
+        let direction = EXAMPLE.replace(r#"srcPort="out""#, r#"srcPort="self_in""#);
+        assert!(matches!(
+            parse(&direction),
+            Err(ParseError::PortDirection { channel, .. }) if channel == "ab"
+        ));
+
+        let duplicate = EXAMPLE.replace(r#"name="aa""#, r#"name="ab""#);
+        assert!(matches!(parse(&duplicate), Err(ParseError::DuplicateChannel(c)) if c == "ab"));
+
+        assert!(matches!(parse("<sdf3>"), Err(ParseError::Xml(_))));
+    }
+
+    #[test]
+    fn architecture() {
+        let platform = r#"  </applicationGraph>
+  <architectureGraph name="platform">
+    <tile name="t0">
+      <processor name="cpu" type="p1">
+        <arbitration type="TDMA" wheelsize="100"/>
+      </processor>
+      <memory name="mem" size="1024"/>
+      <networkInterface name="ni" nrConnections="4" inBandwidth="96" outBandwidth="96"/>
+    </tile>
+    <tile name="t1">
+      <processor name="cpu" type="p2"/>
+      <memory name="mem" size="512"/>
+    </tile>
+    <connection name="link" srcTile="t0" dstTile="t1" delay="3"/>
+  </architectureGraph>
+  <mapping appGraph="example" archGraph="platform">
+    <tile name="t0">
+      <processor name="cpu">
+        <actor name="a"/>
+        <schedule>
+          <state actor="a"/>
+        </schedule>
+      </processor>
+      <memory name="mem">
+        <actor name="a"/>
+        <channel name="aa"/>
+        <channel name="ab"/>
+      </memory>
+    </tile>
+    <tile name="t1">
+      <processor name="cpu">
+        <actor name="b"/>
+      </processor>
+      <memory name="mem">
+        <channel name="ab"/>
+      </memory>
+    </tile>
+    <connection name="link">
+      <channel name="ab"/>
+    </connection>
+  </mapping>"#;
+        let xml = EXAMPLE.replace("  </applicationGraph>", platform);
+        let graph = parse(&xml).unwrap();
+        let architecture = graph.architecture.as_ref().unwrap();
+        assert_eq!(architecture.tiles, ["t0", "t1"]);
+        assert_eq!(architecture.memory_sizes(), [1024, 512]);
+        assert_eq!(architecture.processors[1].processor_type, "p2");
+        assert_eq!(
+            (
+                architecture.connections[0].source,
+                architecture.connections[0].delay
+            ),
+            (0, 3)
+        );
+
+        let mapping = graph.mapping.as_ref().unwrap();
+        assert_eq!(mapping.states, [Some(0), None]);
+        assert_eq!(mapping.buffers, [vec![0, 1], vec![0]]);
+        assert_eq!(mapping.connections, [Some(0), None]);
+        let processor = mapping.processor_assignment::<1>().unwrap();
+        assert_eq!(processor((1, [0].into())), 1);
+        let hsdf = graph.mdsdf().0.into_hsdf();
+        assert_eq!(
+            hsdf.actors().map(processor).collect::<Vec<_>>(),
+            [0, 0, 0, 1, 1]
+        );
+
+        let mut written = Vec::new();
+        graph.write_xml(&mut written).unwrap();
+        assert_eq!(
+            parse(std::str::from_utf8(&written).unwrap()).unwrap(),
+            graph
+        );
+
+        let unknown = xml.replace(
+            r#"<memory name="mem">
+        <channel name="ab"/>"#,
+            r#"<memory name="ram">
+        <channel name="ab"/>"#,
+        );
+        assert!(matches!(
+            parse(&unknown),
+            Err(ParseError::UnknownMemory { tile, memory }) if tile == "t1" && memory == "ram"
+        ));
+        let mapping = &platform[platform.find("  <mapping").unwrap()..];
+        let missing = EXAMPLE.replace("</sdf3>", &format!("{mapping}\n</sdf3>"));
+        assert!(matches!(
+            parse(&missing),
+            Err(ParseError::MissingArchitecture)
+        ));
+
+        let duplicate = xml.replace(
+            r#"<tile name="t1">
+      <processor name="cpu" type="p2"/>"#,
+            r#"<tile name="t0">
+      <processor name="cpu" type="p2"/>"#,
+        );
+        assert!(matches!(parse(&duplicate), Err(ParseError::DuplicateTile(t)) if t == "t0"));
+    }
 }
@@ -0,0 +1,401 @@
+use crate::architecture::{Architecture, Mapping};
+use crate::{
+    Actor, Channel, ChannelProperties, FromMdsdfError, Port, PortDirection, ProcessorType,
+    Sdf3Graph,
+};
+use mdsdf::Mdsdf;
+use std::io::{self, Write};
+
+/// Processor type that [`Sdf3Graph::from_mdsdf`] gives the execution times.
+const PROCESSOR: &str = "default";
+
+/// Escapes `s` for use in a double quoted attribute value.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Sdf3Graph {
+    /// Graph with the actors and channels of `sdf`, in the same order. Every channel gets its
+    /// own output port `out{c}` and input port `in{c}`, and every actor runs on a single
+    /// processor type with the given execution time. `names` and `execution_times` need one
+    /// entry per actor.
+    pub fn from_mdsdf(
+        sdf: &Mdsdf<1>,
+        name: &str,
+        names: &[String],
+        execution_times: &[usize],
+    ) -> Result<Self, FromMdsdfError> {
+        if names.len() != sdf.n_actors() || execution_times.len() != sdf.n_actors() {
+            return Err(FromMdsdfError::ActorCount {
+                actors: sdf.n_actors(),
+                names: names.len(),
+                execution_times: execution_times.len(),
+            });
+        }
+
+        let mut actors = names
+            .iter()
+            .zip(execution_times)
+            .map(|(name, execution_time)| Actor {
+                name: name.clone(),
+                ports: Vec::new(),
+                processors: vec![ProcessorType {
+                    name: PROCESSOR.to_string(),
+                    default: true,
+                    execution_time: *execution_time,
+                    state_size: None,
+                }],
+            })
+            .collect::<Vec<_>>();
+        let channels = sdf
+            .channels()
+            .map(|(c, channel)| {
+                let initial_tokens = usize::try_from(channel.initial_tokens[0]).map_err(|_| {
+                    FromMdsdfError::NegativeTokens {
+                        channel: c,
+                        initial_tokens: channel.initial_tokens[0],
+                    }
+                })?;
+                let source_port = actors[channel.source].ports.len();
+                actors[channel.source].ports.push(Port {
+                    name: format!("out{c}"),
+                    direction: PortDirection::Out,
+                    rate: channel.production_rate[0],
+                });
+                let target_port = actors[channel.target].ports.len();
+                actors[channel.target].ports.push(Port {
+                    name: format!("in{c}"),
+                    direction: PortDirection::In,
+                    rate: channel.consumption_rate[0],
+                });
+                Ok(Channel {
+                    name: format!("ch{c}"),
+                    source: channel.source,
+                    source_port,
+                    target: channel.target,
+                    target_port,
+                    initial_tokens: Some(initial_tokens),
+                    properties: ChannelProperties::default(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            name: name.to_string(),
+            actors,
+            channels,
+            throughput: None,
+            architecture: None,
+            mapping: None,
+        })
+    }
+
+    /// Writes the graph as an SDF3 document of type `sdf` that [`crate::parse`] reads back
+    /// into the same graph.
+    pub fn write_xml(&self, mut w: impl Write) -> io::Result<()> {
+        let name = escape(&self.name);
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, r#"<sdf3 type="sdf" version="1.0">"#)?;
+        writeln!(w, r#"  <applicationGraph name="{name}">"#)?;
+        writeln!(w, r#"    <sdf name="{name}" type="{name}">"#)?;
+        for actor in self.actors.iter() {
+            let name = escape(&actor.name);
+            writeln!(w, r#"      <actor name="{name}" type="{name}">"#)?;
+            for port in actor.ports.iter() {
+                let direction = match port.direction {
+                    PortDirection::In => "in",
+                    PortDirection::Out => "out",
+                };
+                writeln!(
+                    w,
+                    r#"        <port name="{}" type="{direction}" rate="{}"/>"#,
+                    escape(&port.name),
+                    port.rate
+                )?;
+            }
+            writeln!(w, "      </actor>")?;
+        }
+        for channel in self.channels.iter() {
+            let source = &self.actors[channel.source];
+            let target = &self.actors[channel.target];
+            write!(
+                w,
+                r#"      <channel name="{}" srcActor="{}" srcPort="{}" dstActor="{}" dstPort="{}""#,
+                escape(&channel.name),
+                escape(&source.name),
+                escape(&source.ports[channel.source_port].name),
+                escape(&target.name),
+                escape(&target.ports[channel.target_port].name),
+            )?;
+            if let Some(initial_tokens) = channel.initial_tokens {
+                write!(w, r#" initialTokens="{initial_tokens}""#)?;
+            }
+            writeln!(w, "/>")?;
+        }
+        writeln!(w, "    </sdf>")?;
+        writeln!(w, "    <sdfProperties>")?;
+        for actor in self.actors.iter() {
+            writeln!(
+                w,
+                r#"      <actorProperties actor="{}">"#,
+                escape(&actor.name)
+            )?;
+            for processor in actor.processors.iter() {
+                writeln!(
+                    w,
+                    r#"        <processor type="{}" default="{}">"#,
+                    escape(&processor.name),
+                    processor.default
+                )?;
+                writeln!(
+                    w,
+                    r#"          <executionTime time="{}"/>"#,
+                    processor.execution_time
+                )?;
+                if let Some(state_size) = processor.state_size {
+                    writeln!(w, "          <memory>")?;
+                    writeln!(w, r#"            <stateSize max="{state_size}"/>"#)?;
+                    writeln!(w, "          </memory>")?;
+                }
+                writeln!(w, "        </processor>")?;
+            }
+            writeln!(w, "      </actorProperties>")?;
+        }
+        for channel in self.channels.iter() {
+            let ChannelProperties {
+                token_size,
+                buffer_size,
+            } = &channel.properties;
+            if token_size.is_none() && buffer_size.is_none() {
+                continue;
+            }
+            writeln!(
+                w,
+                r#"      <channelProperties channel="{}">"#,
+                escape(&channel.name)
+            )?;
+            if let Some(buffer_size) = buffer_size {
+                write!(w, "        <bufferSize")?;
+                for (attribute, value) in [
+                    ("sz", buffer_size.size),
+                    ("src", buffer_size.source),
+                    ("dst", buffer_size.target),
+                    ("mem", buffer_size.memory),
+                ] {
+                    if let Some(value) = value {
+                        write!(w, r#" {attribute}="{value}""#)?;
+                    }
+                }
+                writeln!(w, "/>")?;
+            }
+            if let Some(token_size) = token_size {
+                writeln!(w, r#"        <tokenSize sz="{token_size}"/>"#)?;
+            }
+            writeln!(w, "      </channelProperties>")?;
+        }
+        if let Some(throughput) = self.throughput {
+            writeln!(w, "      <graphProperties>")?;
+            writeln!(w, "        <timeConstraints>")?;
+            writeln!(w, "          <throughput>{throughput}</throughput>")?;
+            writeln!(w, "        </timeConstraints>")?;
+            writeln!(w, "      </graphProperties>")?;
+        }
+        writeln!(w, "    </sdfProperties>")?;
+        writeln!(w, "  </applicationGraph>")?;
+        if let Some(architecture) = &self.architecture {
+            self.write_architecture(&mut w, architecture)?;
+            if let Some(mapping) = &self.mapping {
+                self.write_mapping(&mut w, architecture, mapping)?;
+            }
+        }
+        writeln!(w, "</sdf3>")
+    }
+
+    fn write_architecture(
+        &self,
+        w: &mut impl Write,
+        architecture: &Architecture,
+    ) -> io::Result<()> {
+        writeln!(
+            w,
+            r#"  <architectureGraph name="{}">"#,
+            escape(&architecture.name)
+        )?;
+        for (t, tile) in architecture.tiles.iter().enumerate() {
+            writeln!(w, r#"    <tile name="{}">"#, escape(tile))?;
+            for processor in architecture.processors.iter().filter(|p| p.tile == t) {
+                writeln!(
+                    w,
+                    r#"      <processor name="{}" type="{}"/>"#,
+                    escape(&processor.name),
+                    escape(&processor.processor_type)
+                )?;
+            }
+            for memory in architecture.memories.iter().filter(|m| m.tile == t) {
+                writeln!(
+                    w,
+                    r#"      <memory name="{}" size="{}"/>"#,
+                    escape(&memory.name),
+                    memory.size
+                )?;
+            }
+            writeln!(w, "    </tile>")?;
+        }
+        for connection in architecture.connections.iter() {
+            writeln!(
+                w,
+                r#"    <connection name="{}" srcTile="{}" dstTile="{}" delay="{}"/>"#,
+                escape(&connection.name),
+                escape(&architecture.tiles[connection.source]),
+                escape(&architecture.tiles[connection.target]),
+                connection.delay
+            )?;
+        }
+        writeln!(w, "  </architectureGraph>")
+    }
+
+    fn write_mapping(
+        &self,
+        w: &mut impl Write,
+        architecture: &Architecture,
+        mapping: &Mapping,
+    ) -> io::Result<()> {
+        let actor = |a: usize| format!(r#"<actor name="{}"/>"#, escape(&self.actors[a].name));
+        let channel = |c: usize| format!(r#"<channel name="{}"/>"#, escape(&self.channels[c].name));
+        let write_mapped =
+            |w: &mut dyn Write, indent: &str, element: &str, name: &str, mapped: Vec<String>| {
+                if mapped.is_empty() {
+                    return writeln!(w, r#"{indent}<{element} name="{}"/>"#, escape(name));
+                }
+                writeln!(w, r#"{indent}<{element} name="{}">"#, escape(name))?;
+                for m in mapped {
+                    writeln!(w, "{indent}  {m}")?;
+                }
+                writeln!(w, "{indent}</{element}>")
+            };
+
+        writeln!(
+            w,
+            r#"  <mapping appGraph="{}" archGraph="{}">"#,
+            escape(&self.name),
+            escape(&architecture.name)
+        )?;
+        for (t, tile) in architecture.tiles.iter().enumerate() {
+            writeln!(w, r#"    <tile name="{}">"#, escape(tile))?;
+            for (p, processor) in architecture.processors.iter().enumerate() {
+                if processor.tile != t {
+                    continue;
+                }
+                let actors = (0..self.actors.len())
+                    .filter(|a| mapping.processors[*a] == Some(p))
+                    .map(actor)
+                    .collect();
+                write_mapped(w, "      ", "processor", &processor.name, actors)?;
+            }
+            for (m, memory) in architecture.memories.iter().enumerate() {
+                if memory.tile != t {
+                    continue;
+                }
+                let actors = (0..self.actors.len())
+                    .filter(|a| mapping.states[*a] == Some(m))
+                    .map(actor);
+                let channels = (0..self.channels.len())
+                    .filter(|c| mapping.buffers[*c].contains(&m))
+                    .map(channel);
+                write_mapped(
+                    w,
+                    "      ",
+                    "memory",
+                    &memory.name,
+                    actors.chain(channels).collect(),
+                )?;
+            }
+            writeln!(w, "    </tile>")?;
+        }
+        for (c, connection) in architecture.connections.iter().enumerate() {
+            let channels = (0..self.channels.len())
+                .filter(|ch| mapping.connections[*ch] == Some(c))
+                .map(channel)
+                .collect();
+            write_mapped(w, "    ", "connection", &connection.name, channels)?;
+        }
+        writeln!(w, "  </mapping>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, tests::EXAMPLE, FromMdsdfError, Sdf3Graph};
+    use mdsdf::{Channel, Mdsdf};
+
+    #[test]
+    fn round_trip() {
+        let graph = parse(EXAMPLE).unwrap();
+        let mut xml = Vec::new();
+        graph.write_xml(&mut xml).unwrap();
+        assert_eq!(parse(std::str::from_utf8(&xml).unwrap()).unwrap(), graph);
+
+        let mut sdf = Mdsdf::<1>::new(2);
+        for (source, target, production_rate, consumption_rate, initial_tokens) in
+            [(0, 1, 2, 3, 0), (1, 0, 3, 2, 6), (1, 1, 1, 1, 1)]
+        {
+            sdf.add_channel(Channel {
+                production_rate: [production_rate].into(),
+                consumption_rate: [consumption_rate].into(),
+                source,
+                target,
+                initial_tokens: [initial_tokens].into(),
+            });
+        }
+        let names = ["a&b".to_string(), "c".to_string()];
+        assert_eq!(
+            Sdf3Graph::from_mdsdf(&sdf, "g", &names, &[1]),
+            Err(FromMdsdfError::ActorCount {
+                actors: 2,
+                names: 2,
+                execution_times: 1,
+            })
+        );
+        let graph = Sdf3Graph::from_mdsdf(&sdf, "g", &names, &[1, 2]).unwrap();
+        let mut xml = Vec::new();
+        graph.write_xml(&mut xml).unwrap();
+        let parsed = parse(std::str::from_utf8(&xml).unwrap()).unwrap();
+        assert_eq!(parsed, graph);
+        assert_eq!(parsed.actors[0].name, "a&b");
+        assert_eq!(parsed.actors[1].execution_time("default"), Some(2));
+
+        let (written, _) = parsed.mdsdf();
+        for ((_, a), (_, b)) in sdf.channels().zip(written.channels()) {
+            assert_eq!(
+                (a.source, a.target, a.production_rate, a.consumption_rate),
+                (b.source, b.target, b.production_rate, b.consumption_rate)
+            );
+            assert_eq!(a.initial_tokens, b.initial_tokens);
+        }
+
+        let negative = sdf.add_channel(Channel {
+            production_rate: [1].into(),
+            consumption_rate: [1].into(),
+            source: 0,
+            target: 0,
+            initial_tokens: [-1].into(),
+        });
+        assert_eq!(
+            Sdf3Graph::from_mdsdf(&sdf, "g", &names, &[1, 2]),
+            Err(FromMdsdfError::NegativeTokens {
+                channel: negative,
+                initial_tokens: -1,
+            })
+        );
+    }
+}